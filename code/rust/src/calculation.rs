@@ -0,0 +1,400 @@
+use std::fmt;
+
+use crate::decimal::{Decimal, DecimalError};
+use crate::medication::{Dosage, DosageError};
+use crate::units::{Concentration, FlowRate, Mass, TabletCount, TimeSpan, UnitError, Volume};
+
+// Doses prescribed per kg of body weight or per m² of body surface
+// area, resolved for a patient into a concrete tablet or infusion
+// dosage.  Intermediate results are exact up to the precision of
+// `Decimal`; the roundings to what can actually be given are listed
+// in the resolution.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PatientParameters {
+    weight_kg: Decimal,
+    height_cm: Decimal,
+    age_years: u32
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BsaFormula {
+    // sqrt(height × weight / 3600)
+    Mosteller,
+    // 0.007184 × weight^0.425 × height^0.725
+    DuBois,
+    // 0.024265 × weight^0.5378 × height^0.3964, for children
+    Haycock
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DoseBasis {
+    BodyWeight,
+    BodySurfaceArea(BsaFormula)
+}
+
+// `amount` per kg or m², given once in each of `slots` (morning,
+// midday, evening, night) for tablets, or as a single infusion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DoseSpec {
+    pub amount: Mass,
+    pub basis: DoseBasis
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DoseForm {
+    // Tablets of `strength` that can be divided into `smallest_part`s,
+    // e.g. `TabletCount::HALF` for tablets with a score line.
+    Tablets { strength: Mass, smallest_part: TabletCount, slots: [bool; 4] },
+    // Infusion over `duration`, on a pump whose rate is set in steps of
+    // `ml_per_hour_step` ml/h.
+    Infusion { concentration: Concentration, duration: TimeSpan, ml_per_hour_step: Decimal }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoundedQuantity {
+    BodySurfaceArea,
+    Tablets,
+    Rate
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rounding {
+    pub quantity: RoundedQuantity,
+    pub exact: Decimal,
+    pub rounded: Decimal
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Resolution {
+    pub dosage: Dosage,
+    // Dose per administration, before rounding to tablets or pump steps.
+    pub dose: Mass,
+    pub roundings: Vec<Rounding>
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CalculationError {
+    NonPositiveWeight,
+    NonPositiveHeight,
+    ZeroStrength,
+    ZeroConcentration,
+    NonPositiveStep,
+    NoSlots,
+    RoundsToZero,
+    Quantity(UnitError),
+    InvalidDosage(DosageError)
+}
+
+impl fmt::Display for CalculationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CalculationError::NonPositiveWeight => write!(f, "weight must be positive"),
+            CalculationError::NonPositiveHeight => write!(f, "height must be positive"),
+            CalculationError::ZeroStrength => write!(f, "tablet strength must be positive"),
+            CalculationError::ZeroConcentration => write!(f, "concentration must be positive"),
+            CalculationError::NonPositiveStep => write!(f, "rounding step must be positive"),
+            CalculationError::NoSlots => write!(f, "tablets must be taken in at least one slot"),
+            CalculationError::RoundsToZero => write!(f, "dose is too small to be given"),
+            CalculationError::Quantity(err) => write!(f, "{err}"),
+            CalculationError::InvalidDosage(err) => write!(f, "invalid dosage: {err}")
+        }
+    }
+}
+
+impl std::error::Error for CalculationError {}
+
+impl From<UnitError> for CalculationError {
+    fn from(err: UnitError) -> Self {
+        CalculationError::Quantity(err)
+    }
+}
+
+impl From<DecimalError> for CalculationError {
+    fn from(err: DecimalError) -> Self {
+        CalculationError::Quantity(err.into())
+    }
+}
+
+impl From<DosageError> for CalculationError {
+    fn from(err: DosageError) -> Self {
+        CalculationError::InvalidDosage(err)
+    }
+}
+
+impl fmt::Display for Rounding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (name, unit) = match self.quantity {
+            RoundedQuantity::BodySurfaceArea => ("body surface area", "m²"),
+            RoundedQuantity::Tablets => ("tablets", "tablets"),
+            RoundedQuantity::Rate => ("rate", "ml/h")
+        };
+        write!(f, "{name} rounded from {} {unit} to {} {unit}", self.exact, self.rounded)
+    }
+}
+
+impl PatientParameters {
+    pub fn new(weight_kg: Decimal, height_cm: Decimal, age_years: u32)
+        -> Result<PatientParameters, CalculationError> {
+        if weight_kg <= Decimal::ZERO {
+            Err(CalculationError::NonPositiveWeight)
+        } else if height_cm <= Decimal::ZERO {
+            Err(CalculationError::NonPositiveHeight)
+        } else {
+            Ok(PatientParameters { weight_kg, height_cm, age_years })
+        }
+    }
+
+    pub fn weight_kg(self) -> Decimal {
+        self.weight_kg
+    }
+
+    pub fn height_cm(self) -> Decimal {
+        self.height_cm
+    }
+
+    pub fn age_years(self) -> u32 {
+        self.age_years
+    }
+
+    // Body surface area in m², to the precision of `Decimal`.
+    pub fn body_surface_area(self, formula: BsaFormula) -> Result<Decimal, CalculationError> {
+        let (w, h) = (self.weight_kg.to_f64(), self.height_cm.to_f64());
+        let bsa = match formula {
+            BsaFormula::Mosteller => (h * w / 3600.0).sqrt(),
+            BsaFormula::DuBois => 0.007184 * w.powf(0.425) * h.powf(0.725),
+            BsaFormula::Haycock => 0.024265 * w.powf(0.5378) * h.powf(0.3964)
+        };
+        Ok(Decimal::try_from_f64((bsa * 1000.0).round() / 1000.0)?)
+    }
+}
+
+impl BsaFormula {
+    // Haycock for children under 18, Mosteller otherwise.
+    pub fn for_age(age_years: u32) -> BsaFormula {
+        if age_years < 18 { BsaFormula::Haycock } else { BsaFormula::Mosteller }
+    }
+}
+
+impl DoseSpec {
+    // Turns the spec into a dosage for `patient`.  The body surface area
+    // is rounded to 0.01 m², tablets to the smallest part they can be
+    // divided into and infusion rates to the pump increment.
+    pub fn resolve(self, patient: PatientParameters, form: DoseForm)
+        -> Result<Resolution, CalculationError> {
+        let mut roundings = Vec::new();
+        let basis = match self.basis {
+            DoseBasis::BodyWeight => patient.weight_kg,
+            DoseBasis::BodySurfaceArea(formula) => {
+                let exact = patient.body_surface_area(formula)?;
+                round_to_step(exact, Decimal::from_units(10), RoundedQuantity::BodySurfaceArea,
+                              &mut roundings)?
+            }
+        };
+        let dose = Mass::from_mg(self.amount.mg().checked_mul(basis)?)?;
+        let dosage = match form {
+            DoseForm::Tablets { strength, smallest_part, slots } => {
+                if strength.mg().is_zero() {
+                    return Err(CalculationError::ZeroStrength);
+                }
+                if !slots.contains(&true) {
+                    return Err(CalculationError::NoSlots);
+                }
+                let exact = dose.mg().checked_div(strength.mg())?;
+                let count = TabletCount::from_decimal(
+                    round_to_step(exact, smallest_part.to_decimal()?, RoundedQuantity::Tablets,
+                                  &mut roundings)?)?;
+                let [morning, midday, evening, night] =
+                    slots.map(|taken| if taken { count } else { TabletCount::ZERO });
+                Dosage::tablet(morning, midday, evening, night)?
+            }
+            DoseForm::Infusion { concentration, duration, ml_per_hour_step } => {
+                if concentration.mg_per_ml().is_zero() {
+                    return Err(CalculationError::ZeroConcentration);
+                }
+                if duration.is_zero() {
+                    return Err(DosageError::ZeroDuration.into());
+                }
+                let volume = Volume::from_ml(dose.mg().checked_div(concentration.mg_per_ml())?)?;
+                let exact = volume.ml().checked_mul_int(60)?
+                    .div_int_rounded(duration.minutes().into())?;
+                let rate = round_to_step(exact, ml_per_hour_step, RoundedQuantity::Rate,
+                                         &mut roundings)?;
+                Dosage::infusion(FlowRate::from_ml_per_hour(rate)?, duration)?
+            }
+        };
+        Ok(Resolution { dosage, dose, roundings })
+    }
+}
+
+// Rounds `exact` to the nearest multiple of `step`, noting the rounding
+// when it changes the value.
+fn round_to_step(exact: Decimal, step: Decimal, quantity: RoundedQuantity,
+                 roundings: &mut Vec<Rounding>) -> Result<Decimal, CalculationError> {
+    if step <= Decimal::ZERO {
+        return Err(CalculationError::NonPositiveStep);
+    }
+    let steps = exact.checked_div(step)?.round_to(0);
+    let rounded = step.checked_mul(steps)?;
+    if rounded.is_zero() {
+        return Err(CalculationError::RoundsToZero);
+    }
+    if rounded != exact {
+        roundings.push(Rounding { quantity, exact, rounded });
+    }
+    Ok(rounded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn infusion_rate_is_kept_as_rounded_to_the_pump_step() {
+        let patient = PatientParameters::new(Decimal::from_units(70_000),
+                                             Decimal::from_units(175_000), 40).unwrap();
+        let spec = DoseSpec {
+            amount: Mass::from_mg(Decimal::from_units(1_000)).unwrap(),
+            basis: DoseBasis::BodyWeight
+        };
+        let form = DoseForm::Infusion {
+            concentration: Concentration::from_mg_per_ml(Decimal::from_units(10_000)).unwrap(),
+            duration: TimeSpan::from_minutes(60),
+            ml_per_hour_step: Decimal::from_units(1_000)
+        };
+        let resolution = spec.resolve(patient, form).unwrap();
+        let Dosage::Infusion(infusion) = resolution.dosage else {
+            panic!("expected an infusion, got {:?}", resolution.dosage);
+        };
+        assert_eq!(infusion.speed().ml_per_hour(), Decimal::from_units(7_000));
+        assert_eq!(resolution.roundings, []);
+    }
+
+    fn mg(units: i64) -> Mass {
+        Mass::from_mg(Decimal::from_units(units)).unwrap()
+    }
+
+    fn adult() -> PatientParameters {
+        PatientParameters::new(Decimal::from_units(70_000), Decimal::from_units(175_000), 40)
+            .unwrap()
+    }
+
+    fn child() -> PatientParameters {
+        PatientParameters::new(Decimal::from_units(23_400), Decimal::from_units(121_000), 7)
+            .unwrap()
+    }
+
+    fn infusion(ml_per_hour_step: Decimal) -> DoseForm {
+        DoseForm::Infusion {
+            concentration: Concentration::from_mg_per_ml(Decimal::from_units(10_000)).unwrap(),
+            duration: TimeSpan::from_minutes(45),
+            ml_per_hour_step
+        }
+    }
+
+    #[test]
+    fn patients_need_a_positive_weight_and_height() {
+        let zero = Decimal::ZERO;
+        assert_eq!(PatientParameters::new(zero, Decimal::from_units(175_000), 40),
+                   Err(CalculationError::NonPositiveWeight));
+        assert_eq!(PatientParameters::new(Decimal::from_units(70_000), Decimal::from_units(-1), 40),
+                   Err(CalculationError::NonPositiveHeight));
+    }
+
+    #[test]
+    fn body_surface_area_matches_the_published_formulas() {
+        let bsa = |patient: PatientParameters, formula| patient.body_surface_area(formula).unwrap();
+        assert_eq!(bsa(adult(), BsaFormula::Mosteller), Decimal::from_units(1_845));
+        assert_eq!(bsa(adult(), BsaFormula::DuBois), Decimal::from_units(1_848));
+        assert_eq!(bsa(adult(), BsaFormula::Haycock), Decimal::from_units(1_847));
+        assert_eq!(bsa(child(), BsaFormula::Mosteller), Decimal::from_units(887));
+        assert_eq!(bsa(child(), BsaFormula::DuBois), Decimal::from_units(888));
+        assert_eq!(bsa(child(), BsaFormula::Haycock), Decimal::from_units(885));
+        assert_eq!(BsaFormula::for_age(17), BsaFormula::Haycock);
+        assert_eq!(BsaFormula::for_age(18), BsaFormula::Mosteller);
+    }
+
+    #[test]
+    fn tablets_are_rounded_to_the_smallest_part() {
+        // 10 mg/kg × 23.4 kg = 234 mg = 2.34 tablets of 100 mg.
+        let spec = DoseSpec { amount: mg(10_000), basis: DoseBasis::BodyWeight };
+        let form = DoseForm::Tablets {
+            strength: mg(100_000),
+            smallest_part: TabletCount::HALF,
+            slots: [true, false, true, false]
+        };
+        let resolution = spec.resolve(child(), form).unwrap();
+        let count = TabletCount::from_decimal(Decimal::from_units(2_500)).unwrap();
+        assert_eq!(resolution.dosage,
+                   Dosage::tablet(count, TabletCount::ZERO, count, TabletCount::ZERO).unwrap());
+        assert_eq!(resolution.dose, mg(234_000));
+        assert_eq!(resolution.roundings, [Rounding {
+            quantity: RoundedQuantity::Tablets,
+            exact: Decimal::from_units(2_340),
+            rounded: Decimal::from_units(2_500)
+        }]);
+        assert_eq!(resolution.roundings[0].to_string(),
+                   "tablets rounded from 2.34 tablets to 2.5 tablets");
+    }
+
+    #[test]
+    fn body_surface_area_is_rounded_before_the_dose_is_computed() {
+        // 100 mg/m² × 1.85 m² = 185 mg = 3.7 tablets of 50 mg.
+        let spec = DoseSpec {
+            amount: mg(100_000),
+            basis: DoseBasis::BodySurfaceArea(BsaFormula::Mosteller)
+        };
+        let form = DoseForm::Tablets {
+            strength: mg(50_000),
+            smallest_part: TabletCount::HALF,
+            slots: [true, false, false, false]
+        };
+        let resolution = spec.resolve(adult(), form).unwrap();
+        assert_eq!(resolution.dose, mg(185_000));
+        let roundings: Vec<_> = resolution.roundings.iter()
+            .map(|r| (r.quantity, r.exact.to_string(), r.rounded.to_string()))
+            .collect();
+        assert_eq!(roundings, [
+            (RoundedQuantity::BodySurfaceArea, "1.845".to_string(), "1.85".to_string()),
+            (RoundedQuantity::Tablets, "3.7".to_string(), "3.5".to_string())
+        ]);
+    }
+
+    #[test]
+    fn doses_below_half_the_smallest_part_round_to_zero() {
+        // 0.5 mg/kg × 23.4 kg = 11.7 mg = 0.117 tablets of 100 mg.
+        let spec = DoseSpec { amount: mg(500), basis: DoseBasis::BodyWeight };
+        let form = DoseForm::Tablets {
+            strength: mg(100_000),
+            smallest_part: TabletCount::QUARTER,
+            slots: [true, true, true, true]
+        };
+        assert_eq!(spec.resolve(child(), form), Err(CalculationError::RoundsToZero));
+        let form = DoseForm::Tablets {
+            strength: mg(100_000),
+            smallest_part: TabletCount::QUARTER,
+            slots: [false; 4]
+        };
+        assert_eq!(spec.resolve(child(), form), Err(CalculationError::NoSlots));
+    }
+
+    #[test]
+    fn infusion_rate_is_rounded_to_a_positive_pump_step() {
+        // 1 mg/kg × 70 kg = 7 ml over 45 min = 9.333 ml/h.
+        let spec = DoseSpec { amount: mg(1_000), basis: DoseBasis::BodyWeight };
+        let resolution = spec.resolve(adult(), infusion(Decimal::from_units(500))).unwrap();
+        let Dosage::Infusion(infusion_dosage) = resolution.dosage else {
+            panic!("expected an infusion, got {:?}", resolution.dosage);
+        };
+        assert_eq!(infusion_dosage.speed().ml_per_hour(), Decimal::from_units(9_500));
+        assert_eq!(resolution.roundings, [Rounding {
+            quantity: RoundedQuantity::Rate,
+            exact: Decimal::from_units(9_333),
+            rounded: Decimal::from_units(9_500)
+        }]);
+        for step in [Decimal::ZERO, Decimal::from_units(-500)] {
+            assert_eq!(spec.resolve(adult(), infusion(step)),
+                       Err(CalculationError::NonPositiveStep));
+        }
+    }
+}
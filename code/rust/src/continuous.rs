@@ -0,0 +1,166 @@
+use chrono::NaiveDateTime;
+
+use crate::medication::{Dosage, DosageError};
+use crate::units::{FlowRate, UnitError, Volume};
+
+// Runs from the first rate change until `end`, or until further
+// notice; each rate change sets the speed from its time on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContinuousInfusion {
+    rate_changes: Vec<RateChange>,
+    end: Option<NaiveDateTime>
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateChange {
+    pub at: NaiveDateTime,
+    pub rate: FlowRate
+}
+
+impl ContinuousInfusion {
+    pub fn new(rate_changes: Vec<RateChange>, end: Option<NaiveDateTime>)
+        -> Result<ContinuousInfusion, DosageError> {
+        let last = rate_changes.last().ok_or(DosageError::NoRate)?;
+        if rate_changes.windows(2).any(|w| w[0].at >= w[1].at) {
+            Err(DosageError::RateChangesOutOfOrder)
+        } else if end.is_some_and(|end| end <= last.at) {
+            Err(DosageError::EndBeforeLastRateChange)
+        } else {
+            Ok(ContinuousInfusion { rate_changes, end })
+        }
+    }
+
+    // In chronological order, never empty.
+    pub fn rate_changes(&self) -> &[RateChange] {
+        &self.rate_changes
+    }
+
+    pub fn start(&self) -> NaiveDateTime {
+        self.rate_changes[0].at
+    }
+
+    // The most recent rate change.
+    pub fn current_rate(&self) -> RateChange {
+        self.rate_changes[self.rate_changes.len() - 1]
+    }
+
+    pub fn end(&self) -> Option<NaiveDateTime> {
+        self.end
+    }
+
+    // The rate at `at`, `None` before the start or after the end.
+    pub fn rate_at(&self, at: NaiveDateTime) -> Option<FlowRate> {
+        if self.end.is_some_and(|end| at >= end) {
+            return None;
+        }
+        self.rate_changes.iter().rev().find(|change| change.at <= at).map(|change| change.rate)
+    }
+
+    // Volume delivered between `from` and `to`.
+    pub fn volume_between(&self, from: NaiveDateTime, to: NaiveDateTime)
+        -> Result<Volume, UnitError> {
+        let to = self.end.map_or(to, |end| to.min(end));
+        let segment_ends = self.rate_changes.iter().skip(1).map(|change| Some(change.at))
+            .chain([None]);
+        self.rate_changes.iter().zip(segment_ends)
+            .try_fold(Volume::ZERO, |total, (change, segment_end)| {
+                let start = change.at.max(from);
+                let stop = segment_end.map_or(to, |e| e.min(to));
+                if stop <= start {
+                    return Ok(total);
+                }
+                let seconds = (stop - start).num_seconds().unsigned_abs();
+                total.checked_add(change.rate.volume_over_seconds(seconds)?)
+            })
+    }
+
+    // Volume delivered up to `now`.
+    pub fn volume_until(&self, now: NaiveDateTime) -> Result<Volume, UnitError> {
+        self.volume_between(self.start(), now)
+    }
+
+    // Titrates the infusion to `rate` from `at` on.
+    pub fn with_rate_change(&self, at: NaiveDateTime, rate: FlowRate)
+        -> Result<ContinuousInfusion, DosageError> {
+        let mut rate_changes = self.rate_changes.clone();
+        rate_changes.push(RateChange { at, rate });
+        ContinuousInfusion::new(rate_changes, self.end)
+    }
+}
+
+impl From<ContinuousInfusion> for Dosage {
+    fn from(infusion: ContinuousInfusion) -> Self {
+        Dosage::ContinuousInfusion(infusion)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::NaiveDate;
+
+    use crate::decimal::Decimal;
+
+    fn at(hour: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 9, 23).unwrap().and_hms_opt(hour, 0, 0).unwrap()
+    }
+
+    fn ml_per_min(units: i64) -> FlowRate {
+        FlowRate::from_ml_per_min(Decimal::from_units(units)).unwrap()
+    }
+
+    fn ml(units: i64) -> Result<Volume, UnitError> {
+        Volume::from_ml(Decimal::from_units(units))
+    }
+
+    // 0.5 ml/min from 8:00, 0.75 ml/min from 14:00.
+    fn heparin(end: Option<NaiveDateTime>) -> ContinuousInfusion {
+        ContinuousInfusion::new(vec![RateChange { at: at(8), rate: ml_per_min(500) }], end).unwrap()
+            .with_rate_change(at(14), ml_per_min(750)).unwrap()
+    }
+
+    #[test]
+    fn invalid_continuous_infusions_are_rejected() {
+        assert_eq!(ContinuousInfusion::new(vec![], None), Err(DosageError::NoRate));
+        let infusion = heparin(Some(at(20)));
+        assert_eq!(infusion.with_rate_change(at(14), ml_per_min(1000)),
+                   Err(DosageError::RateChangesOutOfOrder));
+        assert_eq!(infusion.with_rate_change(at(20), ml_per_min(1000)),
+                   Err(DosageError::EndBeforeLastRateChange));
+    }
+
+    #[test]
+    fn rate_follows_the_latest_change() {
+        let infusion = heparin(Some(at(20)));
+        assert_eq!(infusion.rate_at(at(7)), None);
+        assert_eq!(infusion.rate_at(at(8)), Some(ml_per_min(500)));
+        assert_eq!(infusion.rate_at(at(13)), Some(ml_per_min(500)));
+        assert_eq!(infusion.rate_at(at(14)), Some(ml_per_min(750)));
+        assert_eq!(infusion.rate_at(at(20)), None);
+        assert_eq!(heparin(None).rate_at(NaiveDateTime::MAX), Some(ml_per_min(750)));
+    }
+
+    #[test]
+    fn volume_sums_the_rates_within_the_interval() {
+        let infusion = heparin(None);
+        // 2 hours at 30 ml/h, then 2 hours at 45 ml/h.
+        assert_eq!(infusion.volume_between(at(12), at(16)), ml(150_000));
+        assert_eq!(infusion.volume_between(at(6), at(9)), ml(30_000));
+        assert_eq!(infusion.volume_until(at(14)), ml(180_000));
+        assert_eq!(infusion.volume_until(at(7)), Ok(Volume::ZERO));
+    }
+
+    #[test]
+    fn volume_ends_with_the_infusion() {
+        let infusion = heparin(Some(at(15)));
+        assert_eq!(infusion.volume_between(at(8), at(20)), ml(225_000));
+        assert_eq!(Dosage::from(infusion).total_volume(), ml(225_000));
+        assert_eq!(Dosage::from(heparin(None)).total_volume(), Err(UnitError::Unbounded));
+    }
+
+    #[test]
+    fn interval_ending_before_it_starts_is_empty() {
+        assert_eq!(heparin(None).volume_between(at(16), at(12)), Ok(Volume::ZERO));
+    }
+}
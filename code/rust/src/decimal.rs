@@ -0,0 +1,256 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+// Fixed-point decimal number with `SCALE` digits after the decimal
+// point.  Parsing and formatting are exact, arithmetic is checked.
+
+pub const SCALE: u32 = 3;
+const ONE: i64 = 10_i64.pow(SCALE);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Decimal(i64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecimalError {
+    Invalid,
+    TooPrecise,
+    Overflow
+}
+
+impl fmt::Display for DecimalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecimalError::Invalid => write!(f, "invalid decimal number"),
+            DecimalError::TooPrecise => write!(f, "more than {SCALE} decimal places"),
+            DecimalError::Overflow => write!(f, "decimal number is too large")
+        }
+    }
+}
+
+impl std::error::Error for DecimalError {}
+
+impl Decimal {
+    pub const ZERO: Decimal = Decimal(0);
+
+    // The number `units / 10^SCALE`.
+    pub const fn from_units(units: i64) -> Decimal {
+        Decimal(units)
+    }
+
+    pub fn from_int(n: i64) -> Result<Decimal, DecimalError> {
+        n.checked_mul(ONE).map(Decimal).ok_or(DecimalError::Overflow)
+    }
+
+    pub fn units(self) -> i64 {
+        self.0
+    }
+
+    pub fn is_negative(self) -> bool {
+        self.0 < 0
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn checked_add(self, other: Decimal) -> Result<Decimal, DecimalError> {
+        self.0.checked_add(other.0).map(Decimal).ok_or(DecimalError::Overflow)
+    }
+
+    pub fn checked_sub(self, other: Decimal) -> Result<Decimal, DecimalError> {
+        self.0.checked_sub(other.0).map(Decimal).ok_or(DecimalError::Overflow)
+    }
+
+    pub fn checked_mul_int(self, n: i64) -> Result<Decimal, DecimalError> {
+        self.0.checked_mul(n).map(Decimal).ok_or(DecimalError::Overflow)
+    }
+
+    // Rounds half away from zero to `SCALE` decimal places.
+    pub fn checked_mul(self, other: Decimal) -> Result<Decimal, DecimalError> {
+        let product = i128::from(self.0) * i128::from(other.0);
+        let one = i128::from(ONE);
+        let rounded = match product % one {
+            r if 2 * r.abs() >= one => product / one + product.signum(),
+            _ => product / one
+        };
+        i64::try_from(rounded).map(Decimal).map_err(|_| DecimalError::Overflow)
+    }
+
+    // Rounds half away from zero to `SCALE` decimal places.
+    pub fn checked_div(self, other: Decimal) -> Result<Decimal, DecimalError> {
+        if other.0 == 0 {
+            return Err(DecimalError::Invalid);
+        }
+        let (a, b) = (i128::from(self.0) * i128::from(ONE), i128::from(other.0));
+        let rounded = match a % b {
+            r if 2 * r.abs() >= b.abs() => a / b + if (a < 0) != (b < 0) { -1 } else { 1 },
+            _ => a / b
+        };
+        i64::try_from(rounded).map(Decimal).map_err(|_| DecimalError::Overflow)
+    }
+
+    // Division by an integer, rounding half away from zero to `SCALE`
+    // decimal places.
+    pub fn div_int_rounded(self, n: i64) -> Result<Decimal, DecimalError> {
+        if n == 0 {
+            return Err(DecimalError::Invalid);
+        }
+        div_rounded(self.0, n).map(Decimal).ok_or(DecimalError::Overflow)
+    }
+
+    // Rounds half away from zero; values too large to round away from
+    // zero are rounded towards it instead.
+    pub fn round_to(self, decimals: u32) -> Decimal {
+        if decimals >= SCALE {
+            self
+        } else {
+            let step = 10_i64.pow(SCALE - decimals);
+            Decimal(div_rounded(self.0, step).and_then(|q| q.checked_mul(step))
+                .unwrap_or(self.0 / step * step))
+        }
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / ONE as f64
+    }
+
+    // Goes through the shortest decimal representation of `x`, so
+    // `0.1_f64` becomes exactly 0.1.
+    pub fn try_from_f64(x: f64) -> Result<Decimal, DecimalError> {
+        if x.is_finite() { x.to_string().parse() } else { Err(DecimalError::Invalid) }
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = DecimalError;
+
+    fn from_str(s: &str) -> Result<Decimal, DecimalError> {
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s)
+        };
+        let (int_part, frac_part) = digits.split_once('.').unwrap_or((digits, ""));
+        let all_digits = |p: &str| p.bytes().all(|b| b.is_ascii_digit());
+        if int_part.is_empty() || !all_digits(int_part) || !all_digits(frac_part)
+            || (digits.contains('.') && frac_part.is_empty()) {
+            return Err(DecimalError::Invalid);
+        }
+        let frac_part = frac_part.trim_end_matches('0');
+        if frac_part.len() > SCALE as usize {
+            return Err(DecimalError::TooPrecise);
+        }
+        let mut units: i64 = 0;
+        for b in int_part.bytes().chain(frac_part.bytes())
+            .chain(std::iter::repeat_n(b'0', SCALE as usize - frac_part.len())) {
+            units = units.checked_mul(10)
+                .and_then(|u| u.checked_add(i64::from(b - b'0')))
+                .ok_or(DecimalError::Overflow)?;
+        }
+        Ok(Decimal(if negative { -units } else { units }))
+    }
+}
+
+// Without a precision, prints as few decimal places as needed
+// (`1.5`, `2`); `{:.2}` rounds to the given number of places.
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (value, decimals) = match f.precision() {
+            Some(p) => (self.round_to(p as u32), p),
+            None => (*self, 0)
+        };
+        let sign = if value.0 < 0 { "-" } else { "" };
+        let abs = value.0.unsigned_abs();
+        let int_part = abs / ONE as u64;
+        let frac = format!("{:0width$}", abs % ONE as u64, width = SCALE as usize);
+        let frac = match f.precision() {
+            Some(_) if decimals <= SCALE as usize => frac[..decimals].to_string(),
+            Some(_) => format!("{frac:0<decimals$}"),
+            None => frac.trim_end_matches('0').to_string()
+        };
+        if frac.is_empty() {
+            write!(f, "{sign}{int_part}")
+        } else {
+            write!(f, "{sign}{int_part}.{frac}")
+        }
+    }
+}
+
+// `None` on division by zero and for `i64::MIN / -1`.
+fn div_rounded(a: i64, b: i64) -> Option<i64> {
+    let (q, r) = (a.checked_div(b)?, a.checked_rem(b)?);
+    Some(match (2 * r.unsigned_abs()).cmp(&b.unsigned_abs()) {
+        Ordering::Less => q,
+        _ if (a < 0) != (b < 0) => q - 1,
+        _ => q + 1
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parsing_is_exact() {
+        assert_eq!(dec("1.5"), Decimal::from_units(1500));
+        assert_eq!(dec("-0.025"), Decimal::from_units(-25));
+        assert_eq!(dec("12"), Decimal::from_units(12000));
+        assert_eq!(dec("0.1230"), Decimal::from_units(123));
+        assert_eq!(dec("9223372036854775.807"), Decimal::from_units(i64::MAX));
+    }
+
+    #[test]
+    fn invalid_numbers_are_rejected() {
+        for s in ["", "-", ".5", "1.", "1,5", "1.2.3", "+1", "1e3", " 1"] {
+            assert_eq!(s.parse::<Decimal>(), Err(DecimalError::Invalid), "{s:?}");
+        }
+        assert_eq!("1.2345".parse::<Decimal>(), Err(DecimalError::TooPrecise));
+        assert_eq!("9223372036854775.808".parse::<Decimal>(), Err(DecimalError::Overflow));
+    }
+
+    #[test]
+    fn display_uses_as_few_places_as_needed() {
+        assert_eq!(dec("1.5").to_string(), "1.5");
+        assert_eq!(dec("2").to_string(), "2");
+        assert_eq!(dec("-0.25").to_string(), "-0.25");
+        assert_eq!(Decimal::ZERO.to_string(), "0");
+    }
+
+    #[test]
+    fn display_rounds_to_the_precision() {
+        assert_eq!(format!("{:.2}", dec("1.5")), "1.50");
+        assert_eq!(format!("{:.1}", dec("1.25")), "1.3");
+        assert_eq!(format!("{:.1}", dec("-1.25")), "-1.3");
+        assert_eq!(format!("{:.0}", dec("2.5")), "3");
+        assert_eq!(format!("{:.0}", dec("2.499")), "2");
+        assert_eq!(format!("{:.5}", dec("0.125")), "0.12500");
+    }
+
+    #[test]
+    fn rounding_at_the_limits_does_not_overflow() {
+        let max = Decimal::from_units(i64::MAX);
+        let min = Decimal::from_units(i64::MIN);
+        assert_eq!(format!("{max:.0}"), "9223372036854775");
+        assert_eq!(format!("{min:.1}"), "-9223372036854775.8");
+        assert_eq!(min.div_int_rounded(-1), Err(DecimalError::Overflow));
+        assert_eq!(max.checked_add(Decimal::from_units(1)), Err(DecimalError::Overflow));
+        assert_eq!(max.checked_mul(dec("2")), Err(DecimalError::Overflow));
+        assert_eq!(Decimal::from_int(i64::MAX), Err(DecimalError::Overflow));
+    }
+
+    #[test]
+    fn arithmetic_rounds_half_away_from_zero() {
+        assert_eq!(dec("0.005").checked_mul(dec("0.1")), Ok(dec("0.001")));
+        assert_eq!(dec("-0.005").checked_mul(dec("0.1")), Ok(dec("-0.001")));
+        assert_eq!(dec("1").checked_div(dec("3")), Ok(dec("0.333")));
+        assert_eq!(dec("2").checked_div(dec("-3")), Ok(dec("-0.667")));
+        assert_eq!(dec("1").checked_div(Decimal::ZERO), Err(DecimalError::Invalid));
+        assert_eq!(dec("0.001").div_int_rounded(2), Ok(dec("0.001")));
+        assert_eq!(dec("-0.001").div_int_rounded(2), Ok(dec("-0.001")));
+        assert_eq!(dec("1").div_int_rounded(0), Err(DecimalError::Invalid));
+    }
+}
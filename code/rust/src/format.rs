@@ -0,0 +1,751 @@
+use chrono::{NaiveDate, NaiveDateTime, Weekday};
+
+use crate::continuous::ContinuousInfusion;
+use crate::decimal::Decimal;
+use crate::forms::{DailyQuantity, Eye};
+use crate::loading::Bolus;
+use crate::locale::{fill, Locale};
+use crate::medication::{Dosage, Medication};
+use crate::parse::needs_quotes;
+use crate::plan::{EntryStatus, MedicationPlan, PlanEntry};
+use crate::prn::AsNeeded;
+use crate::recurrence::Recurrence;
+use crate::scale::ScaleRange;
+use crate::taper::TaperPhase;
+use crate::units::{
+    Concentration, FlowRate, InternationalUnits, Mass, TabletCount, TimeSpan, Volume
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TabletStyle {
+    // 1-0-2, 1-0-½-1
+    #[default]
+    Compact,
+    // 1 tablet in the morning, 2 tablets in the evening
+    Verbose
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnitStyle {
+    // 1.5 ml/min for 2h
+    #[default]
+    Abbreviated,
+    // 1.5 milliliters per minute for 2 hours
+    Spelled
+}
+
+// Renders dosages with configurable options.  The default settings
+// produce the same output as `format_dosage`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DosageFormatter {
+    tablet_style: TabletStyle,
+    unit_style: UnitStyle,
+    speed_decimals: Option<usize>,
+    locale: Locale,
+    always_show_night: bool
+}
+
+impl DosageFormatter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn tablet_style(self, tablet_style: TabletStyle) -> Self {
+        DosageFormatter { tablet_style, ..self }
+    }
+
+    pub fn unit_style(self, unit_style: UnitStyle) -> Self {
+        DosageFormatter { unit_style, ..self }
+    }
+
+    // `None` prints the shortest representation of the speed.
+    pub fn speed_decimals(self, speed_decimals: Option<usize>) -> Self {
+        DosageFormatter { speed_decimals, ..self }
+    }
+
+    // Use the 4-slot notation 1-0-1-0 even when no tablets are taken
+    // at night.
+    pub fn always_show_night(self, always_show_night: bool) -> Self {
+        DosageFormatter { always_show_night, ..self }
+    }
+
+    pub fn locale(self, locale: Locale) -> Self {
+        DosageFormatter { locale, ..self }
+    }
+
+    pub fn format_dosage(&self, dosage: &Dosage) -> String {
+        match dosage {
+            Dosage::Tablet(tablet) =>
+                self.format_recurring(self.format_tablet(tablet.slots()), tablet.recurrence()),
+            Dosage::Infusion(infusion) =>
+                self.format_infusion(infusion.speed(), infusion.duration()),
+            Dosage::IntermittentInfusion(infusion) => self.format_intermittent_infusion(
+                infusion.volume(), infusion.run_time(), infusion.interval()
+            ),
+            Dosage::ContinuousInfusion(infusion) => self.format_continuous_infusion(infusion),
+            Dosage::AsNeeded(prn) => self.format_as_needed(prn),
+            Dosage::Tapering(tapering) => self.format_tapering(tapering.phases()),
+            Dosage::SlidingScale(scale) =>
+                self.format_sliding_scale(scale.measurement(), scale.ranges()),
+            Dosage::LoadingDose(loading) =>
+                self.format_loading_dose(loading.bolus(), loading.maintenance()),
+            Dosage::Injection(injection) => self.format_injection(injection.slots()),
+            Dosage::Inhaler(inhaler) => self.format_inhaler(inhaler.slots()),
+            Dosage::EyeDrops(drops) =>
+                self.format_eye_drops(drops.drops(), drops.eye(), drops.times_per_day()),
+            Dosage::Patch(patch) => self.format_patch(patch.patches(), patch.change_interval())
+        }
+    }
+
+    // Paracetamol 500 mg (oral, with food): 1-0-2
+    //
+    // Names that would be read back as something else are quoted:
+    // "Aspirin 100 mg" (oral): 1-0-0
+    pub fn format_medication(&self, m: &Medication) -> String {
+        let catalog = self.locale.catalog();
+        let amounts: Vec<String> = [
+            m.strength().map(|strength| self.format_mass(strength)),
+            m.concentration().map(|concentration| self.format_concentration(concentration))
+        ].into_iter().flatten().collect();
+        let mut name = if needs_quotes(m.drug_name()) {
+            format!("\"{}\"", m.drug_name())
+        } else {
+            m.drug_name().to_string()
+        };
+        if !amounts.is_empty() {
+            name = format!("{name} {}", amounts.join(catalog.list_separator));
+        }
+        let route = m.route().map(|route| catalog.route(route));
+        let instructions = m.instructions().iter().map(|&i| catalog.instruction(i));
+        let details: Vec<&str> = route.into_iter().chain(instructions).collect();
+        if !details.is_empty() {
+            name = fill(catalog.medication_details, &[
+                ("medication", &name),
+                ("details", &details.join(catalog.list_separator))
+            ]);
+        }
+        format!("{name}: {}", self.format_dosage(m.dosage()))
+    }
+
+    fn format_tablet(&self, slots: [TabletCount; 4]) -> String {
+        match self.tablet_style {
+            TabletStyle::Compact =>
+                self.format_compact_slots(slots.map(|count| count.to_string()), slots[3].is_zero()),
+            TabletStyle::Verbose => self.format_verbose_slots(
+                slots.map(|count| (!count.is_zero()).then(|| self.format_tablets(count)))
+            )
+        }
+    }
+
+    // 10-0-8 IU, or 10 IU in the morning, 8 IU in the evening
+    fn format_injection(&self, slots: [InternationalUnits; 4]) -> String {
+        match self.tablet_style {
+            TabletStyle::Compact => {
+                let numbers = slots.map(|units| self.localize_decimal(units.iu()));
+                let numbers = self.format_compact_slots(numbers, slots[3].is_zero());
+                self.format_international_units_number(&numbers)
+            }
+            TabletStyle::Verbose => self.format_verbose_slots(slots.map(|units| {
+                (!units.is_zero()).then(|| self.format_international_units(units))
+            }))
+        }
+    }
+
+    // 2-0-2 puffs, or 2 puffs in the morning, 2 puffs in the evening
+    fn format_inhaler(&self, slots: [u32; 4]) -> String {
+        let catalog = self.locale.catalog();
+        match self.tablet_style {
+            TabletStyle::Compact => {
+                let numbers = self.format_compact_slots(slots.map(|puffs| puffs.to_string()),
+                                                        slots[3] == 0);
+                fill(catalog.puffs_other, &[("count", &numbers)])
+            }
+            TabletStyle::Verbose => self.format_verbose_slots(slots.map(|puffs| {
+                let template = plural(puffs, catalog.puffs_one, catalog.puffs_other);
+                (puffs != 0).then(|| fill(template, &[("count", &puffs.to_string())]))
+            }))
+        }
+    }
+
+    // The patient, then each entry with its dates below it:
+    //
+    // Medication plan for Erika Mustermann, born 1958-03-12
+    // #1 Paracetamol 500 mg (oral): 1-0-2
+    //   from 2024-09-23
+    // #2 Ramipril (oral): 1-0-½-1
+    //   from 2024-09-23 until 2024-10-22, paused since 2024-09-30
+    pub fn format_plan(&self, plan: &MedicationPlan) -> String {
+        let catalog = self.locale.catalog();
+        let mut lines = vec![fill(catalog.plan_header, &[
+            ("patient", plan.patient().name()),
+            ("birth_date", &self.format_date(plan.patient().birth_date()))
+        ])];
+        for entry in plan.entries() {
+            lines.push(format!("{} {}", entry.id(), self.format_medication(entry.medication())));
+            lines.push(format!("  {}", self.format_plan_entry_dates(entry)));
+        }
+        lines.join("\n")
+    }
+
+    fn format_plan_entry_dates(&self, entry: &PlanEntry) -> String {
+        let catalog = self.locale.catalog();
+        let start = self.format_date(entry.start());
+        let period = match entry.stop() {
+            Some(stop) => fill(catalog.plan_from_until, &[
+                ("start", &start), ("stop", &self.format_date(stop))
+            ]),
+            None => fill(catalog.plan_from, &[("start", &start)])
+        };
+        let since = self.format_date(entry.status_since());
+        match entry.status() {
+            EntryStatus::Active => period,
+            EntryStatus::Paused => fill(catalog.plan_paused, &[
+                ("period", &period), ("since", &since)
+            ]),
+            EntryStatus::Discontinued => fill(catalog.plan_discontinued, &[
+                ("period", &period), ("on", &since)
+            ])
+        }
+    }
+
+    // Unless `always_show_night` is set, the night slot is left out of
+    // the compact notation when it is empty: 1-0-½ rather than 1-0-½-0.
+    fn format_compact_slots(&self, slots: [String; 4], night_is_empty: bool) -> String {
+        if night_is_empty && !self.always_show_night {
+            slots[..3].join("-")
+        } else {
+            slots.join("-")
+        }
+    }
+
+    // The amount of each slot in which anything is given: 1 tablet in
+    // the morning, 2 tablets in the evening
+    fn format_verbose_slots(&self, amounts: [Option<String>; 4]) -> String {
+        let catalog = self.locale.catalog();
+        let whens = [catalog.morning, catalog.midday, catalog.evening, catalog.night];
+        let parts: Vec<String> = amounts.into_iter().zip(whens)
+            .filter_map(|(amount, when)| Some(fill(when, &[("tablets", &amount?)])))
+            .collect();
+        parts.join(catalog.list_separator)
+    }
+
+    // 1 drop into each eye 3 times a day
+    fn format_eye_drops(&self, drops: u32, eye: Eye, times_per_day: u32) -> String {
+        let catalog = self.locale.catalog();
+        let drops_template = plural(drops, catalog.drops_one, catalog.drops_other);
+        let times_template = plural(times_per_day, catalog.times_per_day_one,
+                                    catalog.times_per_day_other);
+        fill(catalog.eye_drops, &[
+            ("drops", &fill(drops_template, &[("count", &drops.to_string())])),
+            ("eye", catalog.eye(eye)),
+            ("times", &fill(times_template, &[("count", &times_per_day.to_string())]))
+        ])
+    }
+
+    // 1 patch, changed every 72h
+    fn format_patch(&self, patches: u32, change_interval: TimeSpan) -> String {
+        let catalog = self.locale.catalog();
+        let template = plural(patches, catalog.patches_one, catalog.patches_other);
+        fill(catalog.patch, &[
+            ("patches", &fill(template, &[("count", &patches.to_string())])),
+            ("interval", &self.format_time_span(change_interval))
+        ])
+    }
+
+    // Daily dosages are shown as they are: 1-0-1, but 1-0-0, on Mondays
+    fn format_recurring(&self, dosage: String, recurrence: Recurrence) -> String {
+        let catalog = self.locale.catalog();
+        let recurrence = match recurrence {
+            Recurrence::Daily => return dosage,
+            Recurrence::EveryNDays { days, start } => {
+                let template = plural(days, catalog.every_n_days_one, catalog.every_n_days_other);
+                fill(template, &[("count", &days.to_string()), ("start", &self.format_date(start))])
+            }
+            Recurrence::Weekdays { weekdays } => {
+                let names: Vec<&str> = weekdays.iter(Weekday::Mon)
+                    .map(|day| catalog.weekdays[day.num_days_from_monday() as usize])
+                    .collect();
+                fill(catalog.on_weekdays, &[("weekdays", &names.join(catalog.list_separator))])
+            }
+            Recurrence::Cycle { days_on, days_off, start } => fill(catalog.cycle, &[
+                ("on", &self.format_days(days_on)),
+                ("off", &self.format_days(days_off)),
+                ("start", &self.format_date(start))
+            ])
+        };
+        fill(catalog.recurring, &[("dosage", &dosage), ("recurrence", &recurrence)])
+    }
+
+    // from 2024-09-23: 2-0-0 for 3 days, then 1-0-0 for 3 days
+    fn format_tapering(&self, phases: &[TaperPhase]) -> String {
+        let catalog = self.locale.catalog();
+        let parts: Vec<String> = phases.iter()
+            .map(|phase| fill(catalog.tapering_phase, &[
+                ("dosage", &self.format_dosage(&phase.dosage)),
+                ("days", &self.format_days(phase.days))
+            ]))
+            .collect();
+        fill(catalog.tapering, &[
+            ("start", &self.format_date(phases[0].start)),
+            ("phases", &parts.join(catalog.tapering_separator))
+        ])
+    }
+
+    // Bolus 5 ml, then 1.5 ml/min for 2h
+    fn format_loading_dose(&self, bolus: Bolus, maintenance: &Dosage) -> String {
+        let bolus = match bolus {
+            Bolus::Volume(volume) => self.format_volume(volume),
+            Bolus::Tablets(count) => self.format_tablets(count)
+        };
+        fill(self.locale.catalog().loading_dose, &[
+            ("bolus", &bolus),
+            ("maintenance", &self.format_dosage(maintenance))
+        ])
+    }
+
+    // One line per range, below a header:
+    //
+    // sliding scale by blood glucose (mg/dl):
+    //   blood glucose (mg/dl) | dose
+    //   under 150             | 0 IU
+    //   150 to under 200      | 2 IU
+    //   200 and above         | 4 IU
+    fn format_sliding_scale(&self, measurement: &str, ranges: &[ScaleRange]) -> String {
+        let catalog = self.locale.catalog();
+        let number = |value: Decimal| self.locale.localize_number(&value.to_string());
+        let mut rows = vec![(measurement.to_string(), catalog.scale_dose_header.to_string())];
+        rows.extend(ranges.iter().map(|range| {
+            let values = (range.from.map(number), range.to.map(number));
+            let label = match values {
+                (None, Some(to)) => fill(catalog.range_below, &[("to", &to)]),
+                (Some(from), Some(to)) => fill(catalog.range_between, &[("from", &from), ("to", &to)]),
+                (Some(from), None) => fill(catalog.range_above, &[("from", &from)]),
+                (None, None) => catalog.range_any.to_string()
+            };
+            (label, self.format_international_units(range.dose))
+        }));
+        let width = rows.iter().map(|(label, _)| label.chars().count()).max().unwrap_or(0);
+        let mut lines = vec![fill(catalog.sliding_scale, &[("measurement", measurement)])];
+        lines.extend(rows.iter().map(|(label, dose)| format!("  {label:width$} | {dose}")));
+        lines.join("\n")
+    }
+
+    // 3 tablets, 300 ml, 18 IU, 4 puffs, 2 drops, 0.333 patches
+    pub fn format_daily_quantity(&self, quantity: DailyQuantity) -> String {
+        let catalog = self.locale.catalog();
+        match quantity {
+            DailyQuantity::Tablets(count) => self.format_tablets(count),
+            DailyQuantity::Volume(volume) => self.format_volume(volume),
+            DailyQuantity::Units(units) => self.format_international_units(units),
+            DailyQuantity::Puffs(puffs) =>
+                fill(plural(puffs, catalog.puffs_one, catalog.puffs_other),
+                     &[("count", &puffs.to_string())]),
+            DailyQuantity::Drops(drops) =>
+                fill(plural(drops, catalog.drops_one, catalog.drops_other),
+                     &[("count", &drops.to_string())]),
+            DailyQuantity::Patches(patches) => {
+                let template = if patches == Decimal::from_units(1_000) {
+                    catalog.patches_one
+                } else {
+                    catalog.patches_other
+                };
+                fill(template, &[("count", &self.localize_decimal(patches))])
+            }
+        }
+    }
+
+    pub fn format_mass(&self, mass: Mass) -> String {
+        let catalog = self.locale.catalog();
+        let template = match self.unit_style {
+            UnitStyle::Abbreviated => catalog.mass_abbreviated,
+            UnitStyle::Spelled => catalog.mass_spelled
+        };
+        fill(template, &[("mg", &self.locale.localize_number(&mass.mg().to_string()))])
+    }
+
+    pub fn format_concentration(&self, concentration: Concentration) -> String {
+        let catalog = self.locale.catalog();
+        let template = match self.unit_style {
+            UnitStyle::Abbreviated => catalog.concentration_abbreviated,
+            UnitStyle::Spelled => catalog.concentration_spelled
+        };
+        let mg = concentration.mg_per_ml().to_string();
+        fill(template, &[("mg", &self.locale.localize_number(&mg))])
+    }
+
+    pub fn format_international_units(&self, units: InternationalUnits) -> String {
+        self.format_international_units_number(&self.localize_decimal(units.iu()))
+    }
+
+    fn localize_decimal(&self, value: Decimal) -> String {
+        self.locale.localize_number(&value.to_string())
+    }
+
+    fn format_international_units_number(&self, count: &str) -> String {
+        let catalog = self.locale.catalog();
+        let template = match self.unit_style {
+            UnitStyle::Abbreviated => catalog.international_units_abbreviated,
+            UnitStyle::Spelled => catalog.international_units_spelled
+        };
+        fill(template, &[("count", count)])
+    }
+
+    fn format_days(&self, days: u32) -> String {
+        let catalog = self.locale.catalog();
+        fill(plural(days, catalog.days_one, catalog.days_other), &[("count", &days.to_string())])
+    }
+
+    // 1 tablet, ½ tablet, 1½ tablets
+    fn format_tablets(&self, count: TabletCount) -> String {
+        let catalog = self.locale.catalog();
+        let template = if count.quarters() <= 4 { catalog.tablets_one } else { catalog.tablets_other };
+        fill(template, &[("count", &count.to_string())])
+    }
+
+    fn format_as_needed(&self, prn: &AsNeeded) -> String {
+        fill(self.locale.catalog().as_needed, &[
+            ("dose", &self.format_tablets(prn.dose())),
+            ("indication", prn.indication()),
+            ("max", &self.format_tablets(prn.max_per_day())),
+            ("day", &self.format_time_span(TimeSpan::DAY)),
+            ("interval", &self.format_time_span(prn.min_interval()))
+        ])
+    }
+
+    // 100 ml over 30min every 8h
+    fn format_intermittent_infusion(&self, volume: Volume, run_time: TimeSpan, interval: TimeSpan)
+        -> String {
+        fill(self.locale.catalog().intermittent_infusion, &[
+            ("volume", &self.format_volume(volume)),
+            ("run_time", &self.format_time_span(run_time)),
+            ("interval", &self.format_time_span(interval))
+        ])
+    }
+
+    pub fn format_volume(&self, volume: Volume) -> String {
+        let catalog = self.locale.catalog();
+        let template = match self.unit_style {
+            UnitStyle::Abbreviated => catalog.volume_abbreviated,
+            UnitStyle::Spelled => catalog.volume_spelled
+        };
+        fill(template, &[("volume", &self.locale.localize_number(&volume.ml().to_string()))])
+    }
+
+    fn format_infusion(&self, speed: FlowRate, duration: TimeSpan) -> String {
+        fill(self.locale.catalog().infusion, &[
+            ("rate", &self.format_rate(speed)),
+            ("duration", &self.format_time_span(duration))
+        ])
+    }
+
+    // Shows the most recently set rate: 2 ml/min since 2024-09-23 12:00
+    // until further notice
+    fn format_continuous_infusion(&self, infusion: &ContinuousInfusion) -> String {
+        let catalog = self.locale.catalog();
+        let current = infusion.current_rate();
+        let rate = self.format_rate(current.rate);
+        let since = self.format_date_time(current.at);
+        match infusion.end() {
+            Some(end) => fill(catalog.continuous_infusion_until, &[
+                ("rate", &rate), ("since", &since), ("end", &self.format_date_time(end))
+            ]),
+            None => fill(catalog.continuous_infusion, &[("rate", &rate), ("since", &since)])
+        }
+    }
+
+    // In ml/min, or in ml/h for rates such as 1 ml/h that aren't a whole
+    // number of thousandths ml/min.
+    pub fn format_rate(&self, rate: FlowRate) -> String {
+        let catalog = self.locale.catalog();
+        let (speed, template) = match (rate.ml_per_min(), self.unit_style) {
+            (Some(speed), UnitStyle::Abbreviated) => (speed, catalog.rate_abbreviated),
+            (Some(speed), UnitStyle::Spelled) => (speed, catalog.rate_spelled),
+            (None, UnitStyle::Abbreviated) =>
+                (rate.ml_per_hour(), catalog.rate_per_hour_abbreviated),
+            (None, UnitStyle::Spelled) => (rate.ml_per_hour(), catalog.rate_per_hour_spelled)
+        };
+        let speed = match self.speed_decimals {
+            Some(decimals) => format!("{speed:.decimals$}"),
+            None => format!("{speed}")
+        };
+        fill(template, &[("speed", &self.locale.localize_number(&speed))])
+    }
+
+    pub fn format_date(&self, date: NaiveDate) -> String {
+        date.format(self.locale.catalog().date_format).to_string()
+    }
+
+    pub fn format_date_time(&self, at: NaiveDateTime) -> String {
+        at.format(self.locale.catalog().date_time_format).to_string()
+    }
+
+    // 2h, 30min, 1h 30min
+    pub fn format_time_span(&self, span: TimeSpan) -> String {
+        let catalog = self.locale.catalog();
+        let (hours, minutes) = (span.minutes() / 60, span.minutes() % 60);
+        let (hours_template, minutes_template) = match self.unit_style {
+            UnitStyle::Abbreviated =>
+                (catalog.hours_abbreviated, catalog.minutes_abbreviated),
+            UnitStyle::Spelled =>
+                (plural(hours, catalog.hours_one, catalog.hours_other),
+                 plural(minutes, catalog.minutes_one, catalog.minutes_other))
+        };
+        let mut parts = Vec::new();
+        if hours != 0 || minutes == 0 {
+            parts.push(fill(hours_template, &[("count", &hours.to_string())]));
+        }
+        if minutes != 0 {
+            parts.push(fill(minutes_template, &[("count", &minutes.to_string())]));
+        }
+        parts.join(" ")
+    }
+}
+
+fn plural(count: u32, one: &'static str, other: &'static str) -> &'static str {
+    if count == 1 { one } else { other }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::{TimeDelta, WeekdaySet};
+
+    use crate::continuous::RateChange;
+    use crate::medication::Tablet;
+    use crate::plan::Patient;
+    use crate::route::{Instruction, Route};
+
+    fn tablets(slots: [u64; 4]) -> Dosage {
+        let [morning, midday, evening, night] = slots.map(TabletCount::from_quarters);
+        Dosage::tablet(morning, midday, evening, night).unwrap()
+    }
+
+    fn infusion(ml_per_min: i64, minutes: u32) -> Dosage {
+        let speed = FlowRate::from_ml_per_min(Decimal::from_units(ml_per_min)).unwrap();
+        Dosage::infusion(speed, TimeSpan::from_minutes(minutes)).unwrap()
+    }
+
+    fn german() -> DosageFormatter {
+        DosageFormatter::new().locale(Locale::De)
+    }
+
+    #[test]
+    fn compact_tablets_leave_out_an_empty_night() {
+        let f = DosageFormatter::new();
+        assert_eq!(f.format_dosage(&tablets([4, 0, 8, 0])), "1-0-2");
+        assert_eq!(f.format_dosage(&tablets([4, 0, 2, 4])), "1-0-½-1");
+        assert_eq!(f.always_show_night(true).format_dosage(&tablets([4, 0, 8, 0])), "1-0-2-0");
+    }
+
+    #[test]
+    fn verbose_tablets_name_each_slot() {
+        let f = DosageFormatter::new().tablet_style(TabletStyle::Verbose);
+        assert_eq!(f.format_dosage(&tablets([4, 0, 8, 0])),
+                   "1 tablet in the morning, 2 tablets in the evening");
+        assert_eq!(f.format_dosage(&tablets([0, 6, 0, 2])),
+                   "1½ tablets at midday, ½ tablet at night");
+        let f = german().tablet_style(TabletStyle::Verbose);
+        assert_eq!(f.format_dosage(&tablets([4, 0, 8, 0])),
+                   "1 Tablette morgens, 2 Tabletten abends");
+    }
+
+    #[test]
+    fn units_are_abbreviated_or_spelled() {
+        let spelled = DosageFormatter::new().unit_style(UnitStyle::Spelled);
+        assert_eq!(DosageFormatter::new().format_dosage(&infusion(1500, 120)),
+                   "1.5 ml/min for 2h");
+        assert_eq!(spelled.format_dosage(&infusion(1500, 120)),
+                   "1.5 milliliters per minute for 2 hours");
+        assert_eq!(spelled.format_dosage(&infusion(2000, 61)),
+                   "2 milliliters per minute for 1 hour 1 minute");
+        assert_eq!(DosageFormatter::new().format_dosage(&infusion(1000, 90)),
+                   "1 ml/min for 1h 30min");
+    }
+
+    #[test]
+    fn speed_is_rounded_to_the_given_decimals() {
+        let f = DosageFormatter::new();
+        assert_eq!(f.speed_decimals(Some(2)).format_dosage(&infusion(1500, 120)),
+                   "1.50 ml/min for 2h");
+        assert_eq!(f.speed_decimals(Some(0)).format_dosage(&infusion(1500, 120)),
+                   "2 ml/min for 2h");
+        assert_eq!(f.speed_decimals(None).format_dosage(&infusion(1250, 120)),
+                   "1.25 ml/min for 2h");
+    }
+
+    #[test]
+    fn rate_without_exact_ml_per_min_is_shown_per_hour() {
+        let speed = FlowRate::from_ml_per_hour(Decimal::from_units(1000)).unwrap();
+        let dosage = Dosage::infusion(speed, TimeSpan::DAY).unwrap();
+        assert_eq!(DosageFormatter::new().format_dosage(&dosage), "1 ml/h for 24h");
+        assert_eq!(german().unit_style(UnitStyle::Spelled).format_dosage(&dosage),
+                   "1 Milliliter pro Stunde für 24 Stunden");
+    }
+
+    #[test]
+    fn german_uses_its_own_templates_and_decimal_comma() {
+        assert_eq!(german().format_dosage(&infusion(1500, 120)), "1,5 ml/min für 2 h");
+        assert_eq!(german().speed_decimals(Some(2)).format_dosage(&infusion(1500, 30)),
+                   "1,50 ml/min für 30 min");
+        assert_eq!(german().unit_style(UnitStyle::Spelled).format_dosage(&infusion(1500, 60)),
+                   "1,5 Milliliter pro Minute für 1 Stunde");
+        let drops = Dosage::eye_drops(1, Eye::Both, 3).unwrap();
+        assert_eq!(DosageFormatter::new().format_dosage(&drops),
+                   "1 drop into each eye 3 times a day");
+        assert_eq!(german().format_dosage(&drops), "3-mal täglich 1 Tropfen in jedes Auge");
+    }
+
+    #[test]
+    fn medication_shows_strength_route_and_instructions() {
+        let m = Medication::new("Paracetamol", tablets([4, 0, 8, 0]))
+            .with_strength(Mass::from_mg(Decimal::from_units(500_000)).unwrap())
+            .with_route(Route::FeedingTube).unwrap()
+            .with_instruction(Instruction::DissolveInWater);
+        assert_eq!(DosageFormatter::new().format_medication(&m),
+                   "Paracetamol 500 mg (via feeding tube, dissolve in water): 1-0-2");
+        assert_eq!(DosageFormatter::new().unit_style(UnitStyle::Spelled).format_medication(&m),
+                   "Paracetamol 500 milligrams (via feeding tube, dissolve in water): 1-0-2");
+        assert_eq!(german().unit_style(UnitStyle::Spelled).format_medication(&m),
+                   "Paracetamol 500 Milligramm (über Sonde, in Wasser auflösen): 1-0-2");
+    }
+
+    fn day(n: i64) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 9, 23).unwrap() + TimeDelta::days(n)
+    }
+
+    #[test]
+    fn as_needed_names_its_limits() {
+        let dosage = Dosage::as_needed(TabletCount::new(1), TabletCount::new(4),
+                                       TimeSpan::from_hours(6).unwrap(), "pain").unwrap();
+        assert_eq!(DosageFormatter::new().format_dosage(&dosage),
+                   "1 tablet as needed for pain, at most 4 tablets per 24h, at least 6h apart");
+    }
+
+    #[test]
+    fn intermittent_and_continuous_infusions_show_their_rates() {
+        let volume = Volume::from_ml(Decimal::from_units(100_000)).unwrap();
+        let intermittent = Dosage::intermittent_infusion(volume, TimeSpan::from_minutes(30),
+                                                         TimeSpan::from_hours(8).unwrap()).unwrap();
+        assert_eq!(DosageFormatter::new().format_dosage(&intermittent),
+                   "100 ml over 30min every 8h");
+        let at = |hours| day(0).and_hms_opt(hours, 0, 0).unwrap();
+        let rate = |ml_per_min| FlowRate::from_ml_per_min(Decimal::from_units(ml_per_min)).unwrap();
+        let continuous = Dosage::continuous_infusion(vec![
+            RateChange { at: at(8), rate: rate(500) },
+            RateChange { at: at(14), rate: rate(750) }
+        ], None).unwrap();
+        assert_eq!(DosageFormatter::new().format_dosage(&continuous),
+                   "0.75 ml/min since 2024-09-23 14:00 until further notice");
+        assert_eq!(german().format_dosage(&continuous),
+                   "0,75 ml/min seit 23.09.2024 14:00 bis auf Weiteres");
+    }
+
+    #[test]
+    fn recurrence_follows_the_daily_dosage() {
+        let recurring = |slots: [u64; 4], recurrence| {
+            let [morning, midday, evening, night] = slots.map(TabletCount::from_quarters);
+            Dosage::from(Tablet::new(morning, midday, evening, night).unwrap()
+                .with_recurrence(recurrence))
+        };
+        let mondays = Recurrence::weekdays(WeekdaySet::single(Weekday::Mon)).unwrap();
+        let weekly = recurring([4, 0, 0, 0], mondays);
+        assert_eq!(DosageFormatter::new().format_dosage(&weekly), "1-0-0, on Mondays");
+        assert_eq!(german().format_dosage(&weekly), "1-0-0, montags");
+        let cycle = recurring([8, 0, 8, 0], Recurrence::cycle(14, 7, day(0)).unwrap());
+        assert_eq!(DosageFormatter::new().format_dosage(&cycle),
+                   "2-0-2, in cycles of 14 days on and 7 days off from 2024-09-23");
+        assert_eq!(DosageFormatter::new().tablet_style(TabletStyle::Verbose).format_dosage(&cycle),
+                   "2 tablets in the morning, 2 tablets in the evening, \
+                    in cycles of 14 days on and 7 days off from 2024-09-23");
+    }
+
+    #[test]
+    fn tapering_lists_its_phases() {
+        let phases = [8, 6, 4, 2].into_iter().zip(0..).map(|(quarters, n)| TaperPhase {
+            start: day(3 * n),
+            days: 3,
+            dosage: tablets([quarters, 0, 0, 0])
+        }).collect();
+        let dosage = Dosage::tapering(phases).unwrap();
+        assert_eq!(DosageFormatter::new().format_dosage(&dosage),
+                   "from 2024-09-23: 2-0-0 for 3 days, then 1½-0-0 for 3 days, \
+                    then 1-0-0 for 3 days, then ½-0-0 for 3 days");
+        assert_eq!(german().format_dosage(&dosage),
+                   "ab 23.09.2024: 2-0-0 für 3 Tage, dann 1½-0-0 für 3 Tage, \
+                    dann 1-0-0 für 3 Tage, dann ½-0-0 für 3 Tage");
+    }
+
+    #[test]
+    fn sliding_scale_is_a_table() {
+        let bound = |mg_per_dl: i64| Decimal::from_units(mg_per_dl * 1000);
+        let iu = |units: i64| InternationalUnits::from_iu(Decimal::from_units(units)).unwrap();
+        let ranges = vec![
+            ScaleRange { from: None, to: Some(bound(150)), dose: iu(0) },
+            ScaleRange { from: Some(bound(150)), to: Some(bound(200)), dose: iu(2_000) },
+            ScaleRange { from: Some(bound(200)), to: None, dose: iu(4_000) }
+        ];
+        let dosage = Dosage::sliding_scale("blood glucose (mg/dl)", ranges).unwrap();
+        assert_eq!(DosageFormatter::new().format_dosage(&dosage), "\
+sliding scale by blood glucose (mg/dl):
+  blood glucose (mg/dl) | dose
+  under 150             | 0 IU
+  150 to under 200      | 2 IU
+  200 and above         | 4 IU");
+        assert_eq!(german().unit_style(UnitStyle::Spelled).format_dosage(&dosage), "\
+Korrekturschema nach blood glucose (mg/dl):
+  blood glucose (mg/dl) | Dosis
+  unter 150             | 0 Internationale Einheiten
+  150 bis unter 200     | 2 Internationale Einheiten
+  ab 200                | 4 Internationale Einheiten");
+    }
+
+    #[test]
+    fn other_forms_are_localized() {
+        let units = |units| InternationalUnits::from_iu(Decimal::from_units(units)).unwrap();
+        let zero = InternationalUnits::ZERO;
+        let injection = Dosage::injection(zero, zero, zero, units(18_000)).unwrap();
+        assert_eq!(DosageFormatter::new().format_dosage(&injection), "0-0-0-18 IU");
+        assert_eq!(DosageFormatter::new().tablet_style(TabletStyle::Verbose)
+                       .unit_style(UnitStyle::Spelled).format_dosage(&injection),
+                   "18 international units at night");
+        let inhaler = Dosage::inhaler(2, 0, 2, 0).unwrap();
+        assert_eq!(german().format_dosage(&inhaler), "2-0-2 Hübe");
+        let patch = Dosage::patch(1, TimeSpan::from_hours(72).unwrap()).unwrap();
+        assert_eq!(german().format_dosage(&patch), "1 Pflaster, Wechsel alle 72 h");
+    }
+
+    #[test]
+    fn plan_lists_entries_with_their_dates() {
+        let birth_date = NaiveDate::from_ymd_opt(1958, 3, 12).unwrap();
+        let mut plan = MedicationPlan::new(Patient::new("Erika Mustermann", birth_date).unwrap());
+        let paracetamol = Medication::new("Paracetamol", tablets([4, 0, 8, 0]))
+            .with_instruction(Instruction::WithFood);
+        let pain = plan.add(paracetamol, day(0), Some(day(13))).unwrap();
+        let blood_pressure = plan.add(Medication::new("Ramipril", tablets([4, 0, 2, 4])), day(0),
+                                      None).unwrap();
+        let asthma = plan.add(Medication::new("Salbutamol", Dosage::inhaler(2, 0, 2, 0).unwrap()),
+                              day(0), None).unwrap();
+        plan.modify(blood_pressure, Medication::new("Ramipril", tablets([4, 0, 4, 0])), day(3))
+            .unwrap();
+        plan.pause(asthma, day(7)).unwrap();
+        plan.discontinue(pain, day(10)).unwrap();
+        assert_eq!(DosageFormatter::new().format_plan(&plan), "\
+Medication plan for Erika Mustermann, born 1958-03-12
+#1 Paracetamol (oral, with food): 1-0-2
+  from 2024-09-23 until 2024-10-06, discontinued on 2024-10-03
+#2 Ramipril (oral): 1-0-1
+  from 2024-09-23
+#3 Salbutamol (inhaled): 2-0-2 puffs
+  from 2024-09-23, paused since 2024-09-30");
+        plan.resume(asthma, day(10)).unwrap();
+        assert_eq!(german().format_plan(&plan), "\
+Medikationsplan für Erika Mustermann, geboren am 12.03.1958
+#1 Paracetamol (oral, zum Essen): 1-0-2
+  vom 23.09.2024 bis 06.10.2024, abgesetzt am 03.10.2024
+#2 Ramipril (oral): 1-0-1
+  ab 23.09.2024
+#3 Salbutamol (inhalativ): 2-0-2 Hübe
+  ab 23.09.2024");
+    }
+}
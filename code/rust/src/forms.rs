@@ -0,0 +1,331 @@
+use std::fmt;
+
+use crate::decimal::Decimal;
+use crate::format::DosageFormatter;
+use crate::loading::Bolus;
+use crate::medication::{check_slots, Dosage, DosageError};
+use crate::units::{InternationalUnits, TabletCount, TimeSpan, UnitError, Volume};
+
+// Which eyes eye drops go into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Eye {
+    Left,
+    Right,
+    Both
+}
+
+// Injections such as basal insulin, in IU per slot as for tablets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Injection {
+    slots: [InternationalUnits; 4]
+}
+
+// Puffs of a metered-dose inhaler per slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Inhaler {
+    slots: [u32; 4]
+}
+
+// `drops` into each of the eyes given by `eye`, `times_per_day` times a
+// day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EyeDrops {
+    drops: u32,
+    eye: Eye,
+    times_per_day: u32
+}
+
+// `patches` worn at a time, all replaced every `change_interval`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Patch {
+    patches: u32,
+    change_interval: TimeSpan
+}
+
+// What a dosage uses up within 24 hours, in the unit of its form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DailyQuantity {
+    Tablets(TabletCount),
+    Volume(Volume),
+    Units(InternationalUnits),
+    Puffs(u32),
+    Drops(u32),
+    // A fraction for patches worn longer than a day.
+    Patches(Decimal)
+}
+
+impl Eye {
+    pub fn count(self) -> u32 {
+        match self {
+            Eye::Left | Eye::Right => 1,
+            Eye::Both => 2
+        }
+    }
+}
+
+impl Injection {
+    pub fn new(morning: InternationalUnits, midday: InternationalUnits,
+               evening: InternationalUnits, night: InternationalUnits)
+        -> Result<Injection, DosageError> {
+        let slots = [morning, midday, evening, night];
+        check_slots(&slots, |units| units.is_zero())?;
+        Ok(Injection { slots })
+    }
+
+    // Morning, midday, evening and night.
+    pub fn slots(self) -> [InternationalUnits; 4] {
+        self.slots
+    }
+}
+
+impl Inhaler {
+    pub fn new(morning: u32, midday: u32, evening: u32, night: u32)
+        -> Result<Inhaler, DosageError> {
+        let slots = [morning, midday, evening, night];
+        check_slots(&slots, |&puffs| puffs == 0)?;
+        Ok(Inhaler { slots })
+    }
+
+    // Morning, midday, evening and night.
+    pub fn slots(self) -> [u32; 4] {
+        self.slots
+    }
+}
+
+impl EyeDrops {
+    pub fn new(drops: u32, eye: Eye, times_per_day: u32) -> Result<EyeDrops, DosageError> {
+        if drops == 0 {
+            Err(DosageError::ZeroDose)
+        } else if times_per_day == 0 {
+            Err(DosageError::ZeroFrequency)
+        } else {
+            Ok(EyeDrops { drops, eye, times_per_day })
+        }
+    }
+
+    pub fn drops(self) -> u32 {
+        self.drops
+    }
+
+    pub fn eye(self) -> Eye {
+        self.eye
+    }
+
+    pub fn times_per_day(self) -> u32 {
+        self.times_per_day
+    }
+}
+
+impl Patch {
+    pub fn new(patches: u32, change_interval: TimeSpan) -> Result<Patch, DosageError> {
+        if patches == 0 {
+            Err(DosageError::ZeroDose)
+        } else if change_interval.is_zero() {
+            Err(DosageError::ZeroInterval)
+        } else {
+            Ok(Patch { patches, change_interval })
+        }
+    }
+
+    pub fn patches(self) -> u32 {
+        self.patches
+    }
+
+    pub fn change_interval(self) -> TimeSpan {
+        self.change_interval
+    }
+}
+
+impl From<Injection> for Dosage {
+    fn from(injection: Injection) -> Self {
+        Dosage::Injection(injection)
+    }
+}
+
+impl From<Inhaler> for Dosage {
+    fn from(inhaler: Inhaler) -> Self {
+        Dosage::Inhaler(inhaler)
+    }
+}
+
+impl From<EyeDrops> for Dosage {
+    fn from(drops: EyeDrops) -> Self {
+        Dosage::EyeDrops(drops)
+    }
+}
+
+impl From<Patch> for Dosage {
+    fn from(patch: Patch) -> Self {
+        Dosage::Patch(patch)
+    }
+}
+
+impl Dosage {
+    // The quantity used per day, in the sense of `tablets_per_day` and
+    // `volume_per_day` for tablets and infusions.  Eye drops count the
+    // drops into both eyes together, and patches are rounded to the
+    // precision of `Decimal`.  Returns `None` for sliding scales, whose
+    // dose depends on the measurements, and for tapering regimens and
+    // loading doses that mix forms.
+    pub fn daily_quantity(&self) -> Option<Result<DailyQuantity, UnitError>> {
+        self.try_daily_quantity().transpose()
+    }
+
+    fn try_daily_quantity(&self) -> Result<Option<DailyQuantity>, UnitError> {
+        Ok(Some(match self {
+            Dosage::Tablet(_) | Dosage::AsNeeded(_) =>
+                DailyQuantity::Tablets(self.tablets_per_day()?),
+            Dosage::Infusion(_)
+            | Dosage::IntermittentInfusion(_)
+            | Dosage::ContinuousInfusion(_) => DailyQuantity::Volume(self.volume_per_day()?),
+            Dosage::SlidingScale(_) => return Ok(None),
+            Dosage::Injection(injection) => DailyQuantity::Units(injection.slots.into_iter()
+                .try_fold(InternationalUnits::ZERO, InternationalUnits::checked_add)?),
+            Dosage::Inhaler(inhaler) => DailyQuantity::Puffs(inhaler.slots.into_iter()
+                .try_fold(0_u32, u32::checked_add)
+                .ok_or(UnitError::Overflow)?),
+            Dosage::EyeDrops(EyeDrops { drops, eye, times_per_day }) =>
+                DailyQuantity::Drops(drops.checked_mul(*times_per_day)
+                    .and_then(|n| n.checked_mul(eye.count()))
+                    .ok_or(UnitError::Overflow)?),
+            Dosage::Patch(Patch { patches, change_interval }) =>
+                DailyQuantity::Patches(Decimal::from_int((*patches).into())?
+                    .checked_mul_int(TimeSpan::DAY.minutes().into())?
+                    .div_int_rounded(change_interval.minutes().into())?),
+            Dosage::Tapering(tapering) => {
+                let mut largest = None;
+                for phase in tapering.phases() {
+                    largest = match (largest, phase.dosage.try_daily_quantity()?) {
+                        (None, quantity) => quantity,
+                        (Some(a), Some(b)) => larger(a, b),
+                        (Some(_), None) => None
+                    };
+                    if largest.is_none() {
+                        return Ok(None);
+                    }
+                }
+                return Ok(largest);
+            }
+            Dosage::LoadingDose(loading) =>
+                match (loading.bolus(), loading.maintenance().try_daily_quantity()?) {
+                    (Bolus::Tablets(_), Some(DailyQuantity::Tablets(_))) =>
+                        DailyQuantity::Tablets(self.tablets_per_day()?),
+                    (Bolus::Volume(_), Some(DailyQuantity::Volume(_))) =>
+                        DailyQuantity::Volume(self.volume_per_day()?),
+                    _ => return Ok(None)
+                }
+        }))
+    }
+}
+
+// The larger of two quantities of the same form.
+fn larger(a: DailyQuantity, b: DailyQuantity) -> Option<DailyQuantity> {
+    Some(match (a, b) {
+        (DailyQuantity::Tablets(a), DailyQuantity::Tablets(b)) => DailyQuantity::Tablets(a.max(b)),
+        (DailyQuantity::Volume(a), DailyQuantity::Volume(b)) => DailyQuantity::Volume(a.max(b)),
+        (DailyQuantity::Units(a), DailyQuantity::Units(b)) => DailyQuantity::Units(a.max(b)),
+        (DailyQuantity::Puffs(a), DailyQuantity::Puffs(b)) => DailyQuantity::Puffs(a.max(b)),
+        (DailyQuantity::Drops(a), DailyQuantity::Drops(b)) => DailyQuantity::Drops(a.max(b)),
+        (DailyQuantity::Patches(a), DailyQuantity::Patches(b)) => DailyQuantity::Patches(a.max(b)),
+        _ => return None
+    })
+}
+
+impl fmt::Display for DailyQuantity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&DosageFormatter::new().format_daily_quantity(*self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::locale::Locale;
+
+    fn iu(units: i64) -> InternationalUnits {
+        InternationalUnits::from_iu(Decimal::from_units(units)).unwrap()
+    }
+
+    fn daily(dosage: &Dosage) -> DailyQuantity {
+        dosage.daily_quantity().unwrap().unwrap()
+    }
+
+    #[test]
+    fn injections_sum_the_units_of_their_slots() {
+        let zero = InternationalUnits::ZERO;
+        assert_eq!(Dosage::injection(zero, zero, zero, zero), Err(DosageError::ZeroDose));
+        let dosage = Dosage::injection(iu(8_000), zero, iu(6_500), zero).unwrap();
+        assert_eq!(dosage.to_string(), "8-0-6.5 IU");
+        assert_eq!(daily(&dosage), DailyQuantity::Units(iu(14_500)));
+        assert_eq!(daily(&dosage).to_string(), "14.5 IU");
+    }
+
+    #[test]
+    fn inhalers_sum_the_puffs_of_their_slots() {
+        assert_eq!(Dosage::inhaler(0, 0, 0, 0), Err(DosageError::ZeroDose));
+        let dosage = Dosage::inhaler(2, 0, 2, 1).unwrap();
+        assert_eq!(dosage.to_string(), "2-0-2-1 puffs");
+        assert_eq!(daily(&dosage), DailyQuantity::Puffs(5));
+        assert_eq!(daily(&Dosage::inhaler(0, 0, 0, 1).unwrap()).to_string(), "1 puff");
+        let overflowing = Dosage::inhaler(u32::MAX, 1, 0, 0).unwrap();
+        assert_eq!(overflowing.daily_quantity(), Some(Err(UnitError::Overflow)));
+    }
+
+    #[test]
+    fn eye_drops_count_both_eyes() {
+        assert_eq!(Dosage::eye_drops(0, Eye::Left, 1), Err(DosageError::ZeroDose));
+        assert_eq!(Dosage::eye_drops(1, Eye::Left, 0), Err(DosageError::ZeroFrequency));
+        let left = Dosage::eye_drops(1, Eye::Left, 1).unwrap();
+        assert_eq!(left.to_string(), "1 drop into the left eye once a day");
+        assert_eq!(daily(&left).to_string(), "1 drop");
+        let both = Dosage::eye_drops(2, Eye::Both, 3).unwrap();
+        assert_eq!(both.to_string(), "2 drops into each eye 3 times a day");
+        assert_eq!(daily(&both), DailyQuantity::Drops(12));
+    }
+
+    #[test]
+    fn patches_are_counted_per_day_of_wear() {
+        let day = TimeSpan::DAY;
+        assert_eq!(Dosage::patch(0, day), Err(DosageError::ZeroDose));
+        assert_eq!(Dosage::patch(1, TimeSpan::from_minutes(0)), Err(DosageError::ZeroInterval));
+        let daily_patch = Dosage::patch(1, day).unwrap();
+        assert_eq!(daily(&daily_patch).to_string(), "1 patch");
+        let fentanyl = Dosage::patch(1, TimeSpan::from_hours(72).unwrap()).unwrap();
+        assert_eq!(fentanyl.to_string(), "1 patch, changed every 72h");
+        assert_eq!(daily(&fentanyl), DailyQuantity::Patches(Decimal::from_units(333)));
+        assert_eq!(daily(&fentanyl).to_string(), "0.333 patches");
+        let twice = Dosage::patch(2, TimeSpan::from_hours(12).unwrap()).unwrap();
+        assert_eq!(daily(&twice).to_string(), "4 patches");
+    }
+
+    #[test]
+    fn daily_quantity_of_tablets_and_infusions() {
+        let (one, zero) = (TabletCount::new(1), TabletCount::ZERO);
+        let tablet = Dosage::tablet(one, zero, zero, zero).unwrap();
+        assert_eq!(daily(&tablet).to_string(), "1 tablet");
+        let tablets = Dosage::tablet(one, zero, one, zero).unwrap();
+        assert_eq!(daily(&tablets), DailyQuantity::Tablets(TabletCount::new(2)));
+        assert_eq!(daily(&tablets).to_string(), "2 tablets");
+        let intermittent = Dosage::intermittent_infusion(
+            Volume::from_ml(Decimal::from_units(100_000)).unwrap(),
+            TimeSpan::from_minutes(30),
+            TimeSpan::from_hours(8).unwrap()
+        ).unwrap();
+        assert_eq!(daily(&intermittent).to_string(), "300 ml");
+    }
+
+    #[test]
+    fn daily_quantity_is_localized() {
+        let german = DosageFormatter::new().locale(Locale::De);
+        let quantity = |dosage: Result<Dosage, DosageError>| {
+            german.format_daily_quantity(daily(&dosage.unwrap()))
+        };
+        assert_eq!(quantity(Dosage::inhaler(1, 0, 0, 0)), "1 Hub");
+        assert_eq!(quantity(Dosage::inhaler(2, 0, 2, 0)), "4 Hübe");
+        assert_eq!(quantity(Dosage::patch(1, TimeSpan::from_hours(72).unwrap())), "0,333 Pflaster");
+        assert_eq!(quantity(Dosage::injection(iu(1_500), InternationalUnits::ZERO,
+                                              InternationalUnits::ZERO, InternationalUnits::ZERO)),
+                   "1,5 I.E.");
+    }
+}
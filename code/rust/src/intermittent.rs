@@ -0,0 +1,157 @@
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, TimeDelta};
+
+use crate::medication::{Dosage, DosageError};
+use crate::units::{TimeSpan, Volume};
+
+// Repeated short infusions: `volume` run over `run_time`, starting
+// every `interval`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IntermittentInfusion {
+    volume: Volume,
+    run_time: TimeSpan,
+    interval: TimeSpan
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RunWindow {
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime
+}
+
+impl IntermittentInfusion {
+    pub fn new(volume: Volume, run_time: TimeSpan, interval: TimeSpan)
+        -> Result<IntermittentInfusion, DosageError> {
+        if volume.is_zero() {
+            Err(DosageError::ZeroVolume)
+        } else if run_time.is_zero() {
+            Err(DosageError::ZeroDuration)
+        } else if interval.is_zero() {
+            Err(DosageError::ZeroInterval)
+        } else if run_time > interval {
+            Err(DosageError::RunTimeExceedsInterval)
+        } else {
+            Ok(IntermittentInfusion { volume, run_time, interval })
+        }
+    }
+
+    pub fn volume(self) -> Volume {
+        self.volume
+    }
+
+    pub fn run_time(self) -> TimeSpan {
+        self.run_time
+    }
+
+    pub fn interval(self) -> TimeSpan {
+        self.interval
+    }
+
+    // The runs that start on `day`, if the first run started at
+    // `first_start`.  Runs that would end after the last representable
+    // time end at `NaiveDateTime::MAX`.
+    pub fn run_windows(self, first_start: NaiveDateTime, day: NaiveDate) -> Vec<RunWindow> {
+        let run_time = TimeDelta::minutes(self.run_time.minutes().into());
+        let interval = TimeDelta::minutes(self.interval.minutes().into());
+        let day_start = day.and_time(NaiveTime::MIN);
+        let day_end = day_start.checked_add_signed(TimeDelta::days(1));
+        let mut start = Some(first_start);
+        if day_start > first_start {
+            let (elapsed, step) = ((day_start - first_start).num_seconds(), interval.num_seconds());
+            let runs = (elapsed + step - 1) / step;
+            start = TimeDelta::try_seconds(runs * step)
+                .and_then(|offset| first_start.checked_add_signed(offset));
+        }
+        let mut windows = Vec::new();
+        while let Some(run_start) = start.filter(|&s| day_end.is_none_or(|end| s < end)) {
+            let end = run_start.checked_add_signed(run_time).unwrap_or(NaiveDateTime::MAX);
+            windows.push(RunWindow { start: run_start, end });
+            start = run_start.checked_add_signed(interval);
+        }
+        windows
+    }
+}
+
+impl From<IntermittentInfusion> for Dosage {
+    fn from(infusion: IntermittentInfusion) -> Self {
+        Dosage::IntermittentInfusion(infusion)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::decimal::Decimal;
+
+    fn cefuroxime() -> IntermittentInfusion {
+        let volume = Volume::from_ml(Decimal::from_units(100_000)).unwrap();
+        IntermittentInfusion::new(volume, TimeSpan::from_minutes(30), TimeSpan::from_minutes(480))
+            .unwrap()
+    }
+
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 9, day).unwrap().and_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    fn starts(windows: &[RunWindow]) -> Vec<NaiveDateTime> {
+        windows.iter().map(|window| window.start).collect()
+    }
+
+    #[test]
+    fn invalid_intermittent_infusions_are_rejected() {
+        let (volume, half_hour) = (cefuroxime().volume(), TimeSpan::from_minutes(30));
+        assert_eq!(IntermittentInfusion::new(Volume::ZERO, half_hour, TimeSpan::DAY),
+                   Err(DosageError::ZeroVolume));
+        assert_eq!(IntermittentInfusion::new(volume, TimeSpan::from_minutes(0), TimeSpan::DAY),
+                   Err(DosageError::ZeroDuration));
+        assert_eq!(IntermittentInfusion::new(volume, half_hour, TimeSpan::from_minutes(0)),
+                   Err(DosageError::ZeroInterval));
+        assert_eq!(IntermittentInfusion::new(volume, TimeSpan::DAY, half_hour),
+                   Err(DosageError::RunTimeExceedsInterval));
+    }
+
+    #[test]
+    fn runs_on_the_first_day_start_with_the_first_run() {
+        let windows = cefuroxime().run_windows(at(23, 8, 0), at(23, 0, 0).date());
+        assert_eq!(windows, [
+            RunWindow { start: at(23, 8, 0), end: at(23, 8, 30) },
+            RunWindow { start: at(23, 16, 0), end: at(23, 16, 30) }
+        ]);
+    }
+
+    #[test]
+    fn runs_on_later_days_continue_the_interval() {
+        let infusion = cefuroxime();
+        assert_eq!(starts(&infusion.run_windows(at(23, 8, 0), at(24, 0, 0).date())),
+                   [at(24, 0, 0), at(24, 8, 0), at(24, 16, 0)]);
+        assert_eq!(starts(&infusion.run_windows(at(23, 9, 30), at(25, 0, 0).date())),
+                   [at(25, 1, 30), at(25, 9, 30), at(25, 17, 30)]);
+        let second = at(23, 9, 30) + TimeDelta::seconds(30);
+        assert_eq!(starts(&infusion.run_windows(second, at(24, 0, 0).date()))[0],
+                   at(24, 1, 30) + TimeDelta::seconds(30));
+        assert_eq!(infusion.run_windows(at(24, 8, 0), at(23, 0, 0).date()), []);
+    }
+
+    #[test]
+    fn runs_at_the_end_of_the_calendar_do_not_overflow() {
+        let last_day = NaiveDate::MAX;
+        let first_start = last_day.and_hms_opt(23, 45, 0).unwrap();
+        assert_eq!(cefuroxime().run_windows(first_start, last_day), [
+            RunWindow { start: first_start, end: NaiveDateTime::MAX }
+        ]);
+        let first_start = NaiveDateTime::MIN;
+        assert_eq!(cefuroxime().run_windows(first_start, last_day).len(), 3);
+    }
+
+    #[test]
+    fn volume_per_day_counts_the_runs_per_day() {
+        let dosage = Dosage::from(cefuroxime());
+        assert_eq!(dosage.volume_per_day(), Volume::from_ml(Decimal::from_units(300_000)));
+        assert_eq!(dosage.total_volume(), Ok(cefuroxime().volume()));
+        let volume = Volume::from_ml(Decimal::from_units(50_000)).unwrap();
+        let every_seven_hours = IntermittentInfusion::new(volume, TimeSpan::from_minutes(60),
+                                                          TimeSpan::from_minutes(420)).unwrap();
+        assert_eq!(Dosage::from(every_seven_hours).volume_per_day(),
+                   Volume::from_ml(Decimal::from_units(171_429)));
+    }
+}
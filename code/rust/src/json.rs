@@ -0,0 +1,840 @@
+use std::fmt;
+
+use chrono::{NaiveDate, NaiveDateTime, Weekday, WeekdaySet};
+use serde_json::{json, Map, Value};
+
+use crate::continuous::RateChange;
+use crate::decimal::{Decimal, DecimalError};
+use crate::forms::Eye;
+use crate::loading::Bolus;
+use crate::medication::{Dosage, DosageError, Medication, Tablet};
+use crate::recurrence::Recurrence;
+use crate::route::{Instruction, Route};
+use crate::scale::ScaleRange;
+use crate::taper::TaperPhase;
+use crate::units::{
+    Concentration, FlowRate, InternationalUnits, Mass, TabletCount, TimeSpan, UnitError, Volume
+};
+
+// Tagged JSON encoding of medications, as described in the article:
+//
+// { "drugName": "Paracetamol", "dosageKind": "tablet",
+//   "morning": 1, "midday": 0, "evening": 2 }
+//
+// Any medication may carry its `strength` in mg per tablet and its
+// `concentration` in mg/ml.  `route` is left out when it is the
+// dosage's default, e.g. `"sublingual"` or `"feedingTube"` for
+// tablets, and `instructions` is a list such as `["withFood",
+// "doNotCrush"]`.
+//
+// Tablet counts may be quarters (`0.25`, `1.5`); `night` is optional
+// and only written when it isn't zero.  So is `recurrence`, which is
+// left out for daily dosages and otherwise one of
+//
+// { "kind": "everyNDays", "days": 2, "start": "2024-09-23" }
+// { "kind": "weekdays", "weekdays": ["monday", "wednesday", "friday"] }
+// { "kind": "cycle", "daysOn": 21, "daysOff": 7, "start": "2024-09-23" }
+//
+// Infusions carry `speed` in ml/min and `duration` in hours, which may
+// be fractional as long as it amounts to whole minutes.  Rates that
+// aren't a whole number of thousandths ml/min, such as 1 ml/h, are
+// given as `speedPerHour` in ml/h instead of `speed`; this holds for
+// the rates of continuous infusions, too.
+//
+// Intermittent infusions use `"dosageKind": "intermittentInfusion"`
+// with `volume` in ml and `runTime` and `interval` in hours.
+//
+// Continuous infusions use `"dosageKind": "continuousInfusion"` with
+// `rates`, a list of `{ "at": "2024-09-23T08:00:00", "speed": 1.5 }`
+// objects in chronological order, and an optional `end` date-time.
+//
+// Tapering regimens use `"dosageKind": "tapering"` with `phases`, a
+// list of `{ "start": "2024-09-23", "days": 3, "dosage": { ... } }`
+// objects whose `dosage` is encoded like a medication without
+// `drugName`.
+//
+// Loading doses use `"dosageKind": "loadingDose"` with `bolus`, either
+// `{ "volume": 5 }` in ml or `{ "tablets": 2 }`, and the `maintenance`
+// dosage, encoded like the `dosage` of a tapering phase.
+//
+// Sliding scales use `"dosageKind": "slidingScale"` with a
+// `measurement` and `ranges`, a list of `{ "from": 150, "to": 200,
+// "dose": 2 }` objects with the dose in IU; `from` is left out for the
+// first range and `to` for the last one when they are open.
+//
+// As-needed dosages use `"dosageKind": "asNeeded"` with `dose`,
+// `maxPerDay` (both in tablets), `minInterval` (in hours) and
+// `indication`.
+//
+// Injections (`"injection"`, in IU) and inhalers (`"inhaler"`, in
+// puffs) have `morning`, `midday`, `evening` and an optional `night`
+// like tablets.  Eye drops use `"dosageKind": "eyeDrops"` with `drops`,
+// `eye` (`"left"`, `"right"` or `"both"`) and `timesPerDay`; patches
+// `"dosageKind": "patch"` with `patches` and `changeInterval` in hours.
+
+const MEDICATION_FIELDS: &[&str] =
+    &["drugName", "strength", "concentration", "route", "instructions"];
+const TABLET_FIELDS: &[&str] =
+    &["dosageKind", "morning", "midday", "evening", "night", "recurrence"];
+const EVERY_N_DAYS_FIELDS: &[&str] = &["kind", "days", "start"];
+const WEEKDAYS_FIELDS: &[&str] = &["kind", "weekdays"];
+const CYCLE_FIELDS: &[&str] = &["kind", "daysOn", "daysOff", "start"];
+const WEEKDAY_NAMES: [&str; 7] =
+    ["monday", "tuesday", "wednesday", "thursday", "friday", "saturday", "sunday"];
+const INFUSION_FIELDS: &[&str] = &["dosageKind", "speed", "speedPerHour", "duration"];
+const INTERMITTENT_INFUSION_FIELDS: &[&str] =
+    &["dosageKind", "volume", "runTime", "interval"];
+const CONTINUOUS_INFUSION_FIELDS: &[&str] = &["dosageKind", "rates", "end"];
+const TAPERING_FIELDS: &[&str] = &["dosageKind", "phases"];
+const TAPER_PHASE_FIELDS: &[&str] = &["start", "days", "dosage"];
+const SLIDING_SCALE_FIELDS: &[&str] = &["dosageKind", "measurement", "ranges"];
+const SCALE_RANGE_FIELDS: &[&str] = &["from", "to", "dose"];
+const LOADING_DOSE_FIELDS: &[&str] = &["dosageKind", "bolus", "maintenance"];
+const BOLUS_FIELDS: &[&str] = &["volume", "tablets"];
+const RATE_CHANGE_FIELDS: &[&str] = &["at", "speed", "speedPerHour"];
+const AS_NEEDED_FIELDS: &[&str] =
+    &["dosageKind", "dose", "maxPerDay", "minInterval", "indication"];
+const SLOT_FIELDS: &[&str] = &["dosageKind", "morning", "midday", "evening", "night"];
+const EYE_DROPS_FIELDS: &[&str] = &["dosageKind", "drops", "eye", "timesPerDay"];
+const PATCH_FIELDS: &[&str] = &["dosageKind", "patches", "changeInterval"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecodeError {
+    Syntax(String),
+    NotAnObject,
+    MissingField(&'static str),
+    WrongType { field: &'static str, expected: &'static str },
+    OutOfRange(&'static str),
+    UnknownDosageKind(String),
+    UnexpectedField { field: String, dosage_kind: &'static str },
+    InvalidDecimal { field: &'static str, error: DecimalError },
+    InvalidQuantity { field: &'static str, error: UnitError },
+    InvalidDosage(DosageError)
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Syntax(msg) =>
+                write!(f, "invalid JSON: {msg}"),
+            DecodeError::NotAnObject =>
+                write!(f, "medication must be a JSON object"),
+            DecodeError::MissingField(field) =>
+                write!(f, "missing field `{field}`"),
+            DecodeError::WrongType { field, expected } =>
+                write!(f, "field `{field}` must be {expected}"),
+            DecodeError::OutOfRange(field) =>
+                write!(f, "field `{field}` is out of range"),
+            DecodeError::UnknownDosageKind(kind) =>
+                write!(f, "unknown dosageKind `{kind}`"),
+            DecodeError::UnexpectedField { field, dosage_kind } =>
+                write!(f, "field `{field}` is not allowed for dosageKind `{dosage_kind}`"),
+            DecodeError::InvalidDecimal { field, error } =>
+                write!(f, "field `{field}`: {error}"),
+            DecodeError::InvalidQuantity { field, error } =>
+                write!(f, "field `{field}`: {error}"),
+            DecodeError::InvalidDosage(err) =>
+                write!(f, "invalid dosage: {err}")
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+impl From<DosageError> for DecodeError {
+    fn from(err: DosageError) -> Self {
+        DecodeError::InvalidDosage(err)
+    }
+}
+
+pub fn encode_medication(m: &Medication) -> Value {
+    let mut value = encode_dosage(m.dosage());
+    value["drugName"] = json!(m.drug_name());
+    if let Some(strength) = m.strength() {
+        value["strength"] = encode_decimal(strength.mg());
+    }
+    if let Some(concentration) = m.concentration() {
+        value["concentration"] = encode_decimal(concentration.mg_per_ml());
+    }
+    if let Some(route) = m.route().filter(|&route| Some(route) != m.dosage().default_route()) {
+        value["route"] = json!(route_name(route));
+    }
+    if !m.instructions().is_empty() {
+        value["instructions"] = m.instructions().iter()
+            .map(|&instruction| instruction_name(instruction))
+            .collect();
+    }
+    value
+}
+
+fn encode_dosage(dosage: &Dosage) -> Value {
+    match dosage {
+        Dosage::Tablet(tablet) => {
+            let [morning, midday, evening, night] = tablet.slots();
+            let mut value = json!({
+                "dosageKind": "tablet",
+                "morning": encode_tablets(morning),
+                "midday": encode_tablets(midday),
+                "evening": encode_tablets(evening)
+            });
+            if !night.is_zero() {
+                value["night"] = encode_tablets(night);
+            }
+            if tablet.recurrence() != Recurrence::Daily {
+                value["recurrence"] = encode_recurrence(tablet.recurrence());
+            }
+            value
+        }
+        Dosage::Infusion(infusion) => {
+            let mut value = json!({
+                "dosageKind": "infusion",
+                "duration": encode_hours(infusion.duration())
+            });
+            encode_rate(&mut value, infusion.speed());
+            value
+        }
+        Dosage::IntermittentInfusion(infusion) => json!({
+            "dosageKind": "intermittentInfusion",
+            "volume": encode_decimal(infusion.volume().ml()),
+            "runTime": encode_hours(infusion.run_time()),
+            "interval": encode_hours(infusion.interval())
+        }),
+        Dosage::ContinuousInfusion(infusion) => {
+            let rates: Vec<Value> = infusion.rate_changes().iter().map(|change| {
+                let mut value = json!({ "at": encode_date_time(change.at) });
+                encode_rate(&mut value, change.rate);
+                value
+            }).collect();
+            let mut value = json!({
+                "dosageKind": "continuousInfusion",
+                "rates": rates
+            });
+            if let Some(end) = infusion.end() {
+                value["end"] = encode_date_time(end);
+            }
+            value
+        }
+        Dosage::AsNeeded(prn) => json!({
+            "dosageKind": "asNeeded",
+            "dose": encode_tablets(prn.dose()),
+            "maxPerDay": encode_tablets(prn.max_per_day()),
+            "minInterval": encode_hours(prn.min_interval()),
+            "indication": prn.indication()
+        }),
+        Dosage::SlidingScale(scale) => json!({
+            "dosageKind": "slidingScale",
+            "measurement": scale.measurement(),
+            "ranges": scale.ranges().iter().map(|range| {
+                let mut value = json!({ "dose": encode_decimal(range.dose.iu()) });
+                if let Some(from) = range.from {
+                    value["from"] = encode_decimal(from);
+                }
+                if let Some(to) = range.to {
+                    value["to"] = encode_decimal(to);
+                }
+                value
+            }).collect::<Vec<_>>()
+        }),
+        Dosage::LoadingDose(loading) => json!({
+            "dosageKind": "loadingDose",
+            "bolus": match loading.bolus() {
+                Bolus::Volume(volume) => json!({ "volume": encode_decimal(volume.ml()) }),
+                Bolus::Tablets(count) => json!({ "tablets": encode_tablets(count) })
+            },
+            "maintenance": encode_dosage(loading.maintenance())
+        }),
+        Dosage::Tapering(tapering) => json!({
+            "dosageKind": "tapering",
+            "phases": tapering.phases().iter().map(|phase| json!({
+                "start": encode_date(phase.start),
+                "days": phase.days,
+                "dosage": encode_dosage(&phase.dosage)
+            })).collect::<Vec<_>>()
+        }),
+        Dosage::Injection(injection) => encode_slots("injection",
+            injection.slots().map(|units| encode_decimal(units.iu()))),
+        Dosage::Inhaler(inhaler) => encode_slots("inhaler",
+            inhaler.slots().map(|puffs| json!(puffs))),
+        Dosage::EyeDrops(drops) => json!({
+            "dosageKind": "eyeDrops",
+            "drops": drops.drops(),
+            "eye": match drops.eye() {
+                Eye::Left => "left",
+                Eye::Right => "right",
+                Eye::Both => "both"
+            },
+            "timesPerDay": drops.times_per_day()
+        }),
+        Dosage::Patch(patch) => json!({
+            "dosageKind": "patch",
+            "patches": patch.patches(),
+            "changeInterval": encode_hours(patch.change_interval())
+        })
+    }
+}
+
+// `night` is only written when it isn't zero.
+fn encode_slots(dosage_kind: &str, [morning, midday, evening, night]: [Value; 4]) -> Value {
+    let mut value = json!({
+        "dosageKind": dosage_kind,
+        "morning": morning,
+        "midday": midday,
+        "evening": evening
+    });
+    if night.as_f64() != Some(0.0) {
+        value["night"] = night;
+    }
+    value
+}
+
+pub fn decode_medication(value: &Value) -> Result<Medication, DecodeError> {
+    let obj = value.as_object().ok_or(DecodeError::NotAnObject)?;
+    let mut m = Medication::new(string_field(obj, "drugName")?, decode_dosage(obj, MEDICATION_FIELDS)?);
+    if obj.contains_key("strength") {
+        m = m.with_strength(Mass::from_mg(decimal_field(obj, "strength")?)
+            .map_err(|error| DecodeError::InvalidQuantity { field: "strength", error })?);
+    }
+    if obj.contains_key("concentration") {
+        m = m.with_concentration(Concentration::from_mg_per_ml(decimal_field(obj, "concentration")?)
+            .map_err(|error| DecodeError::InvalidQuantity { field: "concentration", error })?);
+    }
+    if obj.contains_key("route") {
+        let name = string_field(obj, "route")?;
+        let route = Route::ALL.into_iter().find(|&route| route_name(route) == name)
+            .ok_or(DecodeError::WrongType { field: "route", expected: "a route name" })?;
+        m = m.with_route(route)?;
+    }
+    if obj.contains_key("instructions") {
+        let names = field(obj, "instructions")?.as_array()
+            .ok_or(DecodeError::WrongType { field: "instructions", expected: "an array" })?;
+        for name in names {
+            let instruction = name.as_str()
+                .and_then(|name| Instruction::ALL.into_iter()
+                    .find(|&instruction| instruction_name(instruction) == name))
+                .ok_or(DecodeError::WrongType {
+                    field: "instructions", expected: "instruction names"
+                })?;
+            m = m.with_instruction(instruction);
+        }
+    }
+    Ok(m)
+}
+
+// `outer` lists the fields of the enclosing medication that may appear
+// alongside those of the dosage.
+fn decode_dosage(obj: &Map<String, Value>, outer: &[&str]) -> Result<Dosage, DecodeError> {
+    Ok(match string_field(obj, "dosageKind")? {
+        "tablet" => {
+            check_fields(obj, "tablet", &[TABLET_FIELDS, outer].concat())?;
+            let night = match obj.get("night") {
+                Some(_) => tablets_field(obj, "night")?,
+                None => TabletCount::ZERO
+            };
+            let tablet = Tablet::new(tablets_field(obj, "morning")?,
+                                     tablets_field(obj, "midday")?,
+                                     tablets_field(obj, "evening")?,
+                                     night)?;
+            match obj.get("recurrence") {
+                Some(value) => tablet.with_recurrence(decode_recurrence(value)?).into(),
+                None => tablet.into()
+            }
+        }
+        "infusion" => {
+            check_fields(obj, "infusion", &[INFUSION_FIELDS, outer].concat())?;
+            Dosage::infusion(rate_field(obj, "infusion")?, hours_field(obj, "duration")?)?
+        }
+        "intermittentInfusion" => {
+            check_fields(obj, "intermittentInfusion",
+                         &[INTERMITTENT_INFUSION_FIELDS, outer].concat())?;
+            let volume = Volume::from_ml(decimal_field(obj, "volume")?)
+                .map_err(|error| DecodeError::InvalidQuantity { field: "volume", error })?;
+            Dosage::intermittent_infusion(volume,
+                                          hours_field(obj, "runTime")?,
+                                          hours_field(obj, "interval")?)?
+        }
+        "continuousInfusion" => {
+            check_fields(obj, "continuousInfusion", &[CONTINUOUS_INFUSION_FIELDS, outer].concat())?;
+            let rates = field(obj, "rates")?.as_array()
+                .ok_or(DecodeError::WrongType { field: "rates", expected: "an array" })?;
+            let rate_changes = rates.iter().map(decode_rate_change).collect::<Result<_, _>>()?;
+            let end = match obj.get("end") {
+                Some(_) => Some(date_time_field(obj, "end")?),
+                None => None
+            };
+            Dosage::continuous_infusion(rate_changes, end)?
+        }
+        "asNeeded" => {
+            check_fields(obj, "asNeeded", &[AS_NEEDED_FIELDS, outer].concat())?;
+            Dosage::as_needed(tablets_field(obj, "dose")?,
+                              tablets_field(obj, "maxPerDay")?,
+                              hours_field(obj, "minInterval")?,
+                              string_field(obj, "indication")?)?
+        }
+        "tapering" => {
+            check_fields(obj, "tapering", &[TAPERING_FIELDS, outer].concat())?;
+            let phases = field(obj, "phases")?.as_array()
+                .ok_or(DecodeError::WrongType { field: "phases", expected: "an array" })?;
+            Dosage::tapering(phases.iter().map(decode_phase).collect::<Result<_, _>>()?)?
+        }
+        "slidingScale" => {
+            check_fields(obj, "slidingScale", &[SLIDING_SCALE_FIELDS, outer].concat())?;
+            let ranges = field(obj, "ranges")?.as_array()
+                .ok_or(DecodeError::WrongType { field: "ranges", expected: "an array" })?;
+            Dosage::sliding_scale(string_field(obj, "measurement")?,
+                                  ranges.iter().map(decode_range).collect::<Result<_, _>>()?)?
+        }
+        "loadingDose" => {
+            check_fields(obj, "loadingDose", &[LOADING_DOSE_FIELDS, outer].concat())?;
+            Dosage::loading_dose(decode_bolus(field(obj, "bolus")?)?,
+                                 nested_dosage_field(obj, "maintenance")?)?
+        }
+        "injection" => {
+            check_fields(obj, "injection", &[SLOT_FIELDS, outer].concat())?;
+            let units = |name| InternationalUnits::from_iu(decimal_field(obj, name)?)
+                .map_err(|error| DecodeError::InvalidQuantity { field: name, error });
+            let night = match obj.get("night") {
+                Some(_) => units("night")?,
+                None => InternationalUnits::ZERO
+            };
+            Dosage::injection(units("morning")?, units("midday")?, units("evening")?, night)?
+        }
+        "inhaler" => {
+            check_fields(obj, "inhaler", &[SLOT_FIELDS, outer].concat())?;
+            let night = match obj.get("night") {
+                Some(_) => count_field(obj, "night")?,
+                None => 0
+            };
+            Dosage::inhaler(count_field(obj, "morning")?,
+                            count_field(obj, "midday")?,
+                            count_field(obj, "evening")?,
+                            night)?
+        }
+        "eyeDrops" => {
+            check_fields(obj, "eyeDrops", &[EYE_DROPS_FIELDS, outer].concat())?;
+            let eye = match string_field(obj, "eye")? {
+                "left" => Eye::Left,
+                "right" => Eye::Right,
+                "both" => Eye::Both,
+                _ => return Err(DecodeError::WrongType {
+                    field: "eye", expected: "`left`, `right` or `both`"
+                })
+            };
+            Dosage::eye_drops(count_field(obj, "drops")?, eye, count_field(obj, "timesPerDay")?)?
+        }
+        "patch" => {
+            check_fields(obj, "patch", &[PATCH_FIELDS, outer].concat())?;
+            Dosage::patch(count_field(obj, "patches")?, hours_field(obj, "changeInterval")?)?
+        }
+        kind => return Err(DecodeError::UnknownDosageKind(kind.to_string()))
+    })
+}
+
+pub fn medication_to_json(m: &Medication) -> String {
+    encode_medication(m).to_string()
+}
+
+pub fn medication_from_json(s: &str) -> Result<Medication, DecodeError> {
+    let value: Value = serde_json::from_str(s).map_err(|e| DecodeError::Syntax(e.to_string()))?;
+    decode_medication(&value)
+}
+
+fn decode_phase(value: &Value) -> Result<TaperPhase, DecodeError> {
+    let obj = value.as_object()
+        .ok_or(DecodeError::WrongType { field: "phases", expected: "an array of objects" })?;
+    check_fields(obj, "tapering", TAPER_PHASE_FIELDS)?;
+    Ok(TaperPhase {
+        start: date_field(obj, "start")?,
+        days: days_field(obj, "days")?,
+        dosage: nested_dosage_field(obj, "dosage")?
+    })
+}
+
+// A dosage within another one, encoded like a medication without
+// `drugName`.
+fn nested_dosage_field(obj: &Map<String, Value>, name: &'static str) -> Result<Dosage, DecodeError> {
+    let dosage = field(obj, name)?.as_object()
+        .ok_or(DecodeError::WrongType { field: name, expected: "an object" })?;
+    decode_dosage(dosage, &[])
+}
+
+fn decode_bolus(value: &Value) -> Result<Bolus, DecodeError> {
+    let obj = value.as_object()
+        .ok_or(DecodeError::WrongType { field: "bolus", expected: "an object" })?;
+    check_fields(obj, "loadingDose", BOLUS_FIELDS)?;
+    match (obj.get("volume"), obj.get("tablets")) {
+        (Some(_), None) => Volume::from_ml(decimal_field(obj, "volume")?)
+            .map(Bolus::Volume)
+            .map_err(|error| DecodeError::InvalidQuantity { field: "volume", error }),
+        (None, Some(_)) => Ok(Bolus::Tablets(tablets_field(obj, "tablets")?)),
+        (None, None) => Err(DecodeError::MissingField("volume")),
+        (Some(_), Some(_)) =>
+            Err(DecodeError::UnexpectedField { field: "tablets".into(), dosage_kind: "loadingDose" })
+    }
+}
+
+fn decode_range(value: &Value) -> Result<ScaleRange, DecodeError> {
+    let obj = value.as_object()
+        .ok_or(DecodeError::WrongType { field: "ranges", expected: "an array of objects" })?;
+    check_fields(obj, "slidingScale", SCALE_RANGE_FIELDS)?;
+    let bound = |name| match obj.get(name) {
+        Some(_) => decimal_field(obj, name).map(Some),
+        None => Ok(None)
+    };
+    Ok(ScaleRange {
+        from: bound("from")?,
+        to: bound("to")?,
+        dose: InternationalUnits::from_iu(decimal_field(obj, "dose")?)
+            .map_err(|error| DecodeError::InvalidQuantity { field: "dose", error })?
+    })
+}
+
+fn decode_recurrence(value: &Value) -> Result<Recurrence, DecodeError> {
+    let obj = value.as_object()
+        .ok_or(DecodeError::WrongType { field: "recurrence", expected: "an object" })?;
+    Ok(match string_field(obj, "kind")? {
+        "everyNDays" => {
+            check_fields(obj, "tablet", EVERY_N_DAYS_FIELDS)?;
+            Recurrence::every_n_days(days_field(obj, "days")?, date_field(obj, "start")?)?
+        }
+        "weekdays" => {
+            check_fields(obj, "tablet", WEEKDAYS_FIELDS)?;
+            let names = field(obj, "weekdays")?.as_array()
+                .ok_or(DecodeError::WrongType { field: "weekdays", expected: "an array" })?;
+            let weekdays = names.iter()
+                .map(|name| name.as_str().and_then(|name| name.parse::<Weekday>().ok())
+                    .ok_or(DecodeError::WrongType { field: "weekdays", expected: "weekday names" }))
+                .collect::<Result<WeekdaySet, _>>()?;
+            Recurrence::weekdays(weekdays)?
+        }
+        "cycle" => {
+            check_fields(obj, "tablet", CYCLE_FIELDS)?;
+            Recurrence::cycle(days_field(obj, "daysOn")?, days_field(obj, "daysOff")?,
+                              date_field(obj, "start")?)?
+        }
+        _ => return Err(DecodeError::WrongType {
+            field: "kind", expected: "`everyNDays`, `weekdays` or `cycle`"
+        })
+    })
+}
+
+fn decode_rate_change(value: &Value) -> Result<RateChange, DecodeError> {
+    let obj = value.as_object()
+        .ok_or(DecodeError::WrongType { field: "rates", expected: "an array of objects" })?;
+    check_fields(obj, "continuousInfusion", RATE_CHANGE_FIELDS)?;
+    Ok(RateChange { at: date_time_field(obj, "at")?, rate: rate_field(obj, "continuousInfusion")? })
+}
+
+fn check_fields(obj: &Map<String, Value>, dosage_kind: &'static str, allowed: &[&str])
+    -> Result<(), DecodeError> {
+    match obj.keys().find(|k| !allowed.contains(&k.as_str())) {
+        Some(field) => Err(DecodeError::UnexpectedField { field: field.clone(), dosage_kind }),
+        None => Ok(())
+    }
+}
+
+fn field<'a>(obj: &'a Map<String, Value>, field: &'static str) -> Result<&'a Value, DecodeError> {
+    obj.get(field).ok_or(DecodeError::MissingField(field))
+}
+
+fn string_field<'a>(obj: &'a Map<String, Value>, name: &'static str) -> Result<&'a str, DecodeError> {
+    field(obj, name)?.as_str()
+        .ok_or(DecodeError::WrongType { field: name, expected: "a string" })
+}
+
+fn tablets_field(obj: &Map<String, Value>, name: &'static str) -> Result<TabletCount, DecodeError> {
+    TabletCount::from_decimal(decimal_field(obj, name)?)
+        .map_err(|error| DecodeError::InvalidQuantity { field: name, error })
+}
+
+fn float_field(obj: &Map<String, Value>, name: &'static str) -> Result<f64, DecodeError> {
+    field(obj, name)?.as_f64()
+        .ok_or(DecodeError::WrongType { field: name, expected: "a number" })
+}
+
+// Goes through the textual representation of the number so that
+// `0.1` is read as exactly 0.1; only numbers in exponent notation such
+// as `1e3` go through `f64`.
+fn decimal_field(obj: &Map<String, Value>, name: &'static str) -> Result<Decimal, DecodeError> {
+    let n = field(obj, name)?.as_number()
+        .ok_or(DecodeError::WrongType { field: name, expected: "a number" })?;
+    match n.to_string().parse() {
+        Err(DecimalError::Invalid) => n.as_f64().map_or(Err(DecimalError::Invalid),
+                                                        Decimal::try_from_f64),
+        parsed => parsed
+    }.map_err(|error| DecodeError::InvalidDecimal { field: name, error })
+}
+
+// `speed` in ml/min or `speedPerHour` in ml/h, but not both.
+fn rate_field(obj: &Map<String, Value>, dosage_kind: &'static str)
+    -> Result<FlowRate, DecodeError> {
+    let rate = match (obj.contains_key("speed"), obj.contains_key("speedPerHour")) {
+        (true, true) => return Err(DecodeError::UnexpectedField {
+            field: "speedPerHour".to_string(), dosage_kind
+        }),
+        (false, true) => FlowRate::from_ml_per_hour(decimal_field(obj, "speedPerHour")?),
+        _ => FlowRate::from_ml_per_min(decimal_field(obj, "speed")?)
+    };
+    Ok(rate.map_err(DosageError::InvalidSpeed)?)
+}
+
+fn hours_field(obj: &Map<String, Value>, name: &'static str) -> Result<TimeSpan, DecodeError> {
+    let hours = float_field(obj, name)?;
+    let minutes = (hours * 60.0).round();
+    if (hours * 60.0 - minutes).abs() > 1e-6 {
+        return Err(DecodeError::WrongType { field: name, expected: "a whole number of minutes" });
+    }
+    if !(0.0..=f64::from(u32::MAX)).contains(&minutes) {
+        return Err(DecodeError::OutOfRange(name));
+    }
+    Ok(TimeSpan::from_minutes(minutes as u32))
+}
+
+fn days_field(obj: &Map<String, Value>, name: &'static str) -> Result<u32, DecodeError> {
+    let days = field(obj, name)?.as_u64()
+        .ok_or(DecodeError::WrongType { field: name, expected: "a whole number of days" })?;
+    u32::try_from(days).map_err(|_| DecodeError::OutOfRange(name))
+}
+
+fn count_field(obj: &Map<String, Value>, name: &'static str) -> Result<u32, DecodeError> {
+    let count = field(obj, name)?.as_u64()
+        .ok_or(DecodeError::WrongType { field: name, expected: "a whole number" })?;
+    u32::try_from(count).map_err(|_| DecodeError::OutOfRange(name))
+}
+
+fn date_field(obj: &Map<String, Value>, name: &'static str) -> Result<NaiveDate, DecodeError> {
+    string_field(obj, name)?.parse()
+        .map_err(|_| DecodeError::WrongType { field: name, expected: "a date" })
+}
+
+fn date_time_field(obj: &Map<String, Value>, name: &'static str)
+    -> Result<NaiveDateTime, DecodeError> {
+    string_field(obj, name)?.parse()
+        .map_err(|_| DecodeError::WrongType { field: name, expected: "a date-time" })
+}
+
+fn route_name(route: Route) -> &'static str {
+    match route {
+        Route::Oral => "oral",
+        Route::Sublingual => "sublingual",
+        Route::FeedingTube => "feedingTube",
+        Route::Intravenous => "intravenous",
+        Route::Subcutaneous => "subcutaneous",
+        Route::Intramuscular => "intramuscular",
+        Route::Inhalation => "inhalation",
+        Route::Ophthalmic => "ophthalmic",
+        Route::Transdermal => "transdermal"
+    }
+}
+
+fn instruction_name(instruction: Instruction) -> &'static str {
+    match instruction {
+        Instruction::WithFood => "withFood",
+        Instruction::BeforeFood => "beforeFood",
+        Instruction::OnEmptyStomach => "onEmptyStomach",
+        Instruction::BeforeSleep => "beforeSleep",
+        Instruction::DoNotCrush => "doNotCrush",
+        Instruction::DissolveInWater => "dissolveInWater",
+        Instruction::AvoidAlcohol => "avoidAlcohol"
+    }
+}
+
+fn encode_recurrence(recurrence: Recurrence) -> Value {
+    match recurrence {
+        Recurrence::Daily => Value::Null,
+        Recurrence::EveryNDays { days, start } => json!({
+            "kind": "everyNDays",
+            "days": days,
+            "start": encode_date(start)
+        }),
+        Recurrence::Weekdays { weekdays } => json!({
+            "kind": "weekdays",
+            "weekdays": weekdays.iter(Weekday::Mon)
+                .map(|day| WEEKDAY_NAMES[day.num_days_from_monday() as usize])
+                .collect::<Vec<_>>()
+        }),
+        Recurrence::Cycle { days_on, days_off, start } => json!({
+            "kind": "cycle",
+            "daysOn": days_on,
+            "daysOff": days_off,
+            "start": encode_date(start)
+        })
+    }
+}
+
+fn encode_date(date: NaiveDate) -> Value {
+    json!(date.format("%Y-%m-%d").to_string())
+}
+
+fn encode_date_time(at: NaiveDateTime) -> Value {
+    json!(at.format("%Y-%m-%dT%H:%M:%S").to_string())
+}
+
+fn encode_hours(span: TimeSpan) -> Value {
+    match span.whole_hours() {
+        Some(hours) => json!(hours),
+        None => json!(f64::from(span.minutes()) / 60.0)
+    }
+}
+
+fn encode_rate(value: &mut Value, rate: FlowRate) {
+    match rate.ml_per_min() {
+        Some(speed) => value["speed"] = encode_decimal(speed),
+        None => value["speedPerHour"] = encode_decimal(rate.ml_per_hour())
+    }
+}
+
+// Written from the fixed-point value, so that no precision is lost.
+fn encode_decimal(x: Decimal) -> Value {
+    Value::Number(x.to_string().parse().expect("decimals are JSON numbers"))
+}
+
+fn encode_tablets(count: TabletCount) -> Value {
+    match count.whole() {
+        Some(whole) => json!(whole),
+        None => json!(count.quarters() as f64 / 4.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(json: &str) -> Result<Medication, DecodeError> {
+        medication_from_json(json)
+    }
+
+    #[test]
+    fn medications_round_trip() {
+        let json = [
+            r#"{"drugName":"Paracetamol","dosageKind":"tablet","morning":1,"midday":0,"evening":2,
+                "strength":500,"instructions":["withFood"]}"#,
+            r#"{"drugName":"Ramipril","dosageKind":"tablet","morning":1,"midday":0,"evening":0.5,
+                "night":1,"route":"feedingTube"}"#,
+            r#"{"drugName":"Methotrexate","dosageKind":"tablet","morning":1,"midday":0,"evening":0,
+                "recurrence":{"kind":"weekdays","weekdays":["monday"]}}"#,
+            r#"{"drugName":"Infliximab","dosageKind":"infusion","speed":1.5,"duration":2,
+                "concentration":2}"#,
+            r#"{"drugName":"Heparin","dosageKind":"continuousInfusion","rates":[
+                {"at":"2024-09-23T08:00:00","speed":0.5},
+                {"at":"2024-09-23T14:00:00","speed":0.75}]}"#,
+            r#"{"drugName":"Morphine","dosageKind":"continuousInfusion","rates":[
+                {"at":"2024-09-23T08:00:00","speedPerHour":1}],"end":"2024-09-24T08:00:00"}"#,
+            r#"{"drugName":"Cefuroxime","dosageKind":"intermittentInfusion","volume":100,
+                "runTime":0.5,"interval":8}"#,
+            r#"{"drugName":"Ibuprofen","dosageKind":"asNeeded","dose":1,"maxPerDay":3,
+                "minInterval":6,"indication":"pain"}"#,
+            r#"{"drugName":"Prednisolone","dosageKind":"tapering","phases":[
+                {"start":"2024-09-23","days":3,"dosage":{"dosageKind":"tablet","morning":2,
+                 "midday":0,"evening":0}},
+                {"start":"2024-09-26","days":3,"dosage":{"dosageKind":"tablet","morning":1,
+                 "midday":0,"evening":0}}]}"#,
+            r#"{"drugName":"Insulin","dosageKind":"slidingScale","measurement":"glucose",
+                "ranges":[{"to":150,"dose":0},{"from":150,"to":200.5,"dose":2},
+                {"from":200.5,"dose":4.5}]}"#,
+            r#"{"drugName":"Amiodarone","dosageKind":"loadingDose","bolus":{"volume":25},
+                "maintenance":{"dosageKind":"infusion","speed":0.5,"duration":24}}"#,
+            r#"{"drugName":"Digoxin","dosageKind":"loadingDose","bolus":{"tablets":1.5},
+                "maintenance":{"dosageKind":"tablet","morning":0.25,"midday":0,"evening":0}}"#,
+            r#"{"drugName":"Insulin glargine","dosageKind":"injection","morning":0,"midday":0,
+                "evening":0,"night":12.5}"#,
+            r#"{"drugName":"Salbutamol","dosageKind":"inhaler","morning":2,"midday":0,"evening":2}"#,
+            r#"{"drugName":"Latanoprost","dosageKind":"eyeDrops","drops":1,"eye":"both",
+                "timesPerDay":1}"#,
+            r#"{"drugName":"Fentanyl","dosageKind":"patch","patches":1,"changeInterval":72}"#
+        ];
+        for json in json {
+            let m = decode(json).unwrap();
+            assert_eq!(decode(&medication_to_json(&m)), Ok(m), "{json}");
+        }
+    }
+
+    #[test]
+    fn decimals_are_written_exactly() {
+        let json = r#"{"drugName":"X","dosageKind":"tablet","morning":1,"midday":0,"evening":0,
+                       "strength":9007199254740.993}"#;
+        let m = decode(json).unwrap();
+        assert_eq!(m.strength().map(|strength| strength.mg()), Some(Decimal::from_units(
+            9_007_199_254_740_993)));
+        let json = medication_to_json(&m);
+        assert!(json.contains(r#""strength":9007199254740.993"#), "{json}");
+        assert_eq!(decode(&json), Ok(m));
+    }
+
+    #[test]
+    fn numbers_in_exponent_notation_are_read() {
+        let json = r#"{"drugName":"X","dosageKind":"tablet","morning":1,"midday":0,"evening":0,
+                       "strength":1.5e2}"#;
+        assert_eq!(decode(json).unwrap().strength().map(|strength| strength.mg()),
+                   Some(Decimal::from_units(150_000)));
+    }
+
+    #[test]
+    fn field_of_another_dosage_kind_is_rejected() {
+        let json = r#"{ "drugName": "Paracetamol", "dosageKind": "tablet",
+                        "morning": 1, "midday": 0, "evening": 2, "speed": 1.5 }"#;
+        assert_eq!(decode(json), Err(DecodeError::UnexpectedField {
+            field: "speed".to_string(), dosage_kind: "tablet"
+        }));
+        let json = r#"{ "drugName": "Infliximab", "dosageKind": "infusion",
+                        "speed": 1.5, "duration": 2, "morning": 1 }"#;
+        assert_eq!(decode(json), Err(DecodeError::UnexpectedField {
+            field: "morning".to_string(), dosage_kind: "infusion"
+        }));
+    }
+
+    #[test]
+    fn missing_and_mistyped_fields_are_named() {
+        let json = r#"{ "drugName": "Paracetamol", "dosageKind": "tablet",
+                        "morning": 1, "evening": 2 }"#;
+        assert_eq!(decode(json), Err(DecodeError::MissingField("midday")));
+        let json = r#"{ "drugName": "Paracetamol", "dosageKind": "tablet",
+                        "morning": "one", "midday": 0, "evening": 2 }"#;
+        assert_eq!(decode(json), Err(DecodeError::WrongType {
+            field: "morning", expected: "a number"
+        }));
+        let json = r#"{ "drugName": "Paracetamol", "dosageKind": "powder" }"#;
+        assert_eq!(decode(json), Err(DecodeError::UnknownDosageKind("powder".to_string())));
+        assert_eq!(decode("[]"), Err(DecodeError::NotAnObject));
+    }
+
+    #[test]
+    fn invalid_quantities_and_dosages_are_rejected() {
+        let json = r#"{ "drugName": "Paracetamol", "dosageKind": "tablet",
+                        "morning": 0.3, "midday": 0, "evening": 2 }"#;
+        assert_eq!(decode(json), Err(DecodeError::InvalidQuantity {
+            field: "morning", error: UnitError::NotQuarterTablets
+        }));
+        let json = r#"{ "drugName": "Paracetamol", "dosageKind": "tablet",
+                        "morning": 0, "midday": 0, "evening": 0 }"#;
+        assert_eq!(decode(json), Err(DecodeError::InvalidDosage(DosageError::ZeroDose)));
+        let json = r#"{ "drugName": "Infliximab", "dosageKind": "infusion",
+                        "speed": 1.5, "duration": 0 }"#;
+        assert_eq!(decode(json), Err(DecodeError::InvalidDosage(DosageError::ZeroDuration)));
+        let json = r#"{ "drugName": "Paracetamol", "dosageKind": "tablet",
+                        "morning": 1, "midday": 0, "evening": 2, "route": "intravenous" }"#;
+        assert_eq!(decode(json), Err(DecodeError::InvalidDosage(DosageError::IncompatibleRoute)));
+        let json = r#"{ "drugName": "Prednisolone", "dosageKind": "tapering", "phases": [
+            { "start": "2024-09-23", "days": 4000000000,
+              "dosage": { "dosageKind": "tablet", "morning": 2, "midday": 0, "evening": 0 } }
+        ] }"#;
+        assert_eq!(decode(json), Err(DecodeError::InvalidDosage(DosageError::PhaseOutOfRange)));
+    }
+
+    #[test]
+    fn rate_without_exact_ml_per_min_round_trips_as_speed_per_hour() {
+        let speed = FlowRate::from_ml_per_hour(Decimal::from_units(1000)).unwrap();
+        let m = Medication::new("Morphine", Dosage::infusion(speed, TimeSpan::DAY).unwrap());
+        let json = medication_to_json(&m);
+        assert!(json.contains(r#""speedPerHour":1}"#), "{json}");
+        assert_eq!(medication_from_json(&json), Ok(m));
+    }
+
+    #[test]
+    fn speed_and_speed_per_hour_together_are_rejected() {
+        let json = r#"{ "drugName": "Morphine", "dosageKind": "infusion", "speed": 1,
+                        "speedPerHour": 60, "duration": 24 }"#;
+        assert_eq!(medication_from_json(json), Err(DecodeError::UnexpectedField {
+            field: "speedPerHour".to_string(), dosage_kind: "infusion"
+        }));
+    }
+}
@@ -0,0 +1,22 @@
+pub mod medication;
+pub mod decimal;
+pub mod calculation;
+pub mod continuous;
+pub mod format;
+pub mod forms;
+pub mod intermittent;
+pub mod json;
+pub mod loading;
+pub mod locale;
+pub mod row;
+pub mod parse;
+pub mod plan;
+pub mod prescription;
+pub mod prn;
+pub mod recurrence;
+pub mod route;
+pub mod scale;
+pub mod schedule;
+pub mod strength;
+pub mod taper;
+pub mod units;
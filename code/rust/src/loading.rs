@@ -0,0 +1,140 @@
+use crate::medication::{Dosage, DosageError};
+use crate::units::{TabletCount, Volume};
+
+// `bolus` given once, then `maintenance`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadingDose {
+    bolus: Bolus,
+    maintenance: Box<Dosage>
+}
+
+// One-off amount given at the start of a regimen with a loading dose.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bolus {
+    Volume(Volume),
+    Tablets(TabletCount)
+}
+
+impl Bolus {
+    pub fn is_zero(self) -> bool {
+        match self {
+            Bolus::Volume(volume) => volume.is_zero(),
+            Bolus::Tablets(count) => count.is_zero()
+        }
+    }
+
+    pub fn volume(self) -> Volume {
+        match self {
+            Bolus::Volume(volume) => volume,
+            Bolus::Tablets(_) => Volume::ZERO
+        }
+    }
+
+    pub fn tablets(self) -> TabletCount {
+        match self {
+            Bolus::Volume(_) => TabletCount::ZERO,
+            Bolus::Tablets(count) => count
+        }
+    }
+}
+
+impl LoadingDose {
+    pub fn new(bolus: Bolus, maintenance: Dosage) -> Result<LoadingDose, DosageError> {
+        if bolus.is_zero() {
+            Err(DosageError::ZeroDose)
+        } else if matches!(maintenance, Dosage::LoadingDose(_)) {
+            Err(DosageError::NestedLoadingDose)
+        } else {
+            Ok(LoadingDose { bolus, maintenance: Box::new(maintenance) })
+        }
+    }
+
+    pub fn bolus(&self) -> Bolus {
+        self.bolus
+    }
+
+    pub fn maintenance(&self) -> &Dosage {
+        &self.maintenance
+    }
+}
+
+impl From<LoadingDose> for Dosage {
+    fn from(loading: LoadingDose) -> Self {
+        Dosage::LoadingDose(loading)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::decimal::Decimal;
+    use crate::format::DosageFormatter;
+    use crate::forms::DailyQuantity;
+    use crate::locale::Locale;
+    use crate::units::{FlowRate, TimeSpan};
+
+    fn ml(units: i64) -> Volume {
+        Volume::from_ml(Decimal::from_units(units)).unwrap()
+    }
+
+    // 1.5 ml/min for 2h, 180 ml
+    fn infusion() -> Dosage {
+        let speed = FlowRate::from_ml_per_min(Decimal::from_units(1_500)).unwrap();
+        Dosage::infusion(speed, TimeSpan::from_minutes(120)).unwrap()
+    }
+
+    fn tablets() -> Dosage {
+        let (one, zero) = (TabletCount::new(1), TabletCount::ZERO);
+        Dosage::tablet(one, zero, one, zero).unwrap()
+    }
+
+    #[test]
+    fn bolus_must_not_be_zero_or_nested() {
+        assert_eq!(LoadingDose::new(Bolus::Volume(Volume::ZERO), infusion()),
+                   Err(DosageError::ZeroDose));
+        assert_eq!(LoadingDose::new(Bolus::Tablets(TabletCount::ZERO), tablets()),
+                   Err(DosageError::ZeroDose));
+        let loading = Dosage::loading_dose(Bolus::Volume(ml(5_000)), infusion()).unwrap();
+        assert_eq!(LoadingDose::new(Bolus::Volume(ml(5_000)), loading),
+                   Err(DosageError::NestedLoadingDose));
+    }
+
+    #[test]
+    fn volume_bolus_is_counted_on_the_first_day() {
+        let dosage = Dosage::loading_dose(Bolus::Volume(ml(5_000)), infusion()).unwrap();
+        assert_eq!(dosage.total_volume(), Ok(ml(185_000)));
+        assert_eq!(dosage.volume_per_day(), Ok(ml(185_000)));
+        assert_eq!(dosage.tablets_per_day(), Ok(TabletCount::ZERO));
+        assert_eq!(dosage.daily_quantity(), Some(Ok(DailyQuantity::Volume(ml(185_000)))));
+    }
+
+    #[test]
+    fn tablet_bolus_is_counted_on_the_first_day() {
+        let dosage = Dosage::loading_dose(Bolus::Tablets(TabletCount::new(2)), tablets()).unwrap();
+        assert_eq!(dosage.tablets_per_day(), Ok(TabletCount::new(4)));
+        assert_eq!(dosage.total_volume(), Ok(Volume::ZERO));
+        assert_eq!(dosage.volume_per_day(), Ok(Volume::ZERO));
+        assert_eq!(dosage.daily_quantity(), Some(Ok(DailyQuantity::Tablets(TabletCount::new(4)))));
+    }
+
+    #[test]
+    fn bolus_of_another_form_has_no_daily_quantity() {
+        let dosage = Dosage::loading_dose(Bolus::Volume(ml(5_000)), tablets()).unwrap();
+        assert_eq!(dosage.total_volume(), Ok(ml(5_000)));
+        assert_eq!(dosage.tablets_per_day(), Ok(TabletCount::new(2)));
+        assert_eq!(dosage.daily_quantity(), None);
+    }
+
+    #[test]
+    fn bolus_is_shown_before_the_maintenance() {
+        let volume = Dosage::loading_dose(Bolus::Volume(ml(5_000)), infusion()).unwrap();
+        assert_eq!(volume.to_string(), "Bolus 5 ml, then 1.5 ml/min for 2h");
+        let tablet = Dosage::loading_dose(Bolus::Tablets(TabletCount::new(2)), tablets()).unwrap();
+        assert_eq!(tablet.to_string(), "Bolus 2 tablets, then 1-0-1");
+        let half = Dosage::loading_dose(Bolus::Tablets(TabletCount::HALF), tablets()).unwrap();
+        assert_eq!(half.to_string(), "Bolus ½ tablet, then 1-0-1");
+        let german = DosageFormatter::new().locale(Locale::De);
+        assert_eq!(german.format_dosage(&volume), "Bolus 5 ml, dann 1,5 ml/min für 2 h");
+    }
+}
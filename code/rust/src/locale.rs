@@ -0,0 +1,420 @@
+// Message catalogs for formatting dosages.  Templates use
+// placeholders such as `{count}`, `{tablets}`, `{speed}` and
+// `{duration}`, which are filled in by `fill`.
+
+use crate::forms::Eye;
+use crate::route::{Instruction, Route};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Locale {
+    #[default]
+    En,
+    De
+}
+
+#[derive(Debug)]
+pub struct Catalog {
+    pub decimal_separator: char,
+    pub list_separator: &'static str,
+    pub tablets_one: &'static str,
+    pub tablets_other: &'static str,
+    pub morning: &'static str,
+    pub midday: &'static str,
+    pub evening: &'static str,
+    pub night: &'static str,
+    pub as_needed: &'static str,
+    pub rate_abbreviated: &'static str,
+    pub rate_spelled: &'static str,
+    pub rate_per_hour_abbreviated: &'static str,
+    pub rate_per_hour_spelled: &'static str,
+    pub infusion: &'static str,
+    pub continuous_infusion: &'static str,
+    pub continuous_infusion_until: &'static str,
+    pub date_time_format: &'static str,
+    pub date_format: &'static str,
+    pub recurring: &'static str,
+    pub every_n_days_one: &'static str,
+    pub every_n_days_other: &'static str,
+    pub on_weekdays: &'static str,
+    pub weekdays: [&'static str; 7],
+    pub cycle: &'static str,
+    pub days_one: &'static str,
+    pub days_other: &'static str,
+    pub tapering: &'static str,
+    pub tapering_phase: &'static str,
+    pub tapering_separator: &'static str,
+    pub loading_dose: &'static str,
+    pub sliding_scale: &'static str,
+    pub scale_dose_header: &'static str,
+    pub range_below: &'static str,
+    pub range_between: &'static str,
+    pub range_above: &'static str,
+    pub range_any: &'static str,
+    pub mass_abbreviated: &'static str,
+    pub mass_spelled: &'static str,
+    pub concentration_abbreviated: &'static str,
+    pub concentration_spelled: &'static str,
+    pub international_units_abbreviated: &'static str,
+    pub international_units_spelled: &'static str,
+    pub intermittent_infusion: &'static str,
+    pub volume_abbreviated: &'static str,
+    pub volume_spelled: &'static str,
+    pub hours_abbreviated: &'static str,
+    pub minutes_abbreviated: &'static str,
+    pub hours_one: &'static str,
+    pub hours_other: &'static str,
+    pub minutes_one: &'static str,
+    pub minutes_other: &'static str,
+    pub routes: RouteNames,
+    pub instructions: InstructionNames,
+    pub medication_details: &'static str,
+    pub puffs_one: &'static str,
+    pub puffs_other: &'static str,
+    pub drops_one: &'static str,
+    pub drops_other: &'static str,
+    pub eyes: EyeNames,
+    pub times_per_day_one: &'static str,
+    pub times_per_day_other: &'static str,
+    pub eye_drops: &'static str,
+    pub patches_one: &'static str,
+    pub patches_other: &'static str,
+    pub patch: &'static str,
+    pub plan_header: &'static str,
+    pub plan_from: &'static str,
+    pub plan_from_until: &'static str,
+    pub plan_paused: &'static str,
+    pub plan_discontinued: &'static str
+}
+
+#[derive(Debug)]
+pub struct RouteNames {
+    pub oral: &'static str,
+    pub sublingual: &'static str,
+    pub feeding_tube: &'static str,
+    pub intravenous: &'static str,
+    pub subcutaneous: &'static str,
+    pub intramuscular: &'static str,
+    pub inhalation: &'static str,
+    pub ophthalmic: &'static str,
+    pub transdermal: &'static str
+}
+
+#[derive(Debug)]
+pub struct InstructionNames {
+    pub with_food: &'static str,
+    pub before_food: &'static str,
+    pub on_empty_stomach: &'static str,
+    pub before_sleep: &'static str,
+    pub do_not_crush: &'static str,
+    pub dissolve_in_water: &'static str,
+    pub avoid_alcohol: &'static str
+}
+
+#[derive(Debug)]
+pub struct EyeNames {
+    pub left: &'static str,
+    pub right: &'static str,
+    pub both: &'static str
+}
+
+pub static EN: Catalog = Catalog {
+    decimal_separator: '.',
+    list_separator: ", ",
+    tablets_one: "{count} tablet",
+    tablets_other: "{count} tablets",
+    morning: "{tablets} in the morning",
+    midday: "{tablets} at midday",
+    evening: "{tablets} in the evening",
+    night: "{tablets} at night",
+    as_needed: "{dose} as needed for {indication}, at most {max} per {day}, \
+                at least {interval} apart",
+    rate_abbreviated: "{speed} ml/min",
+    rate_spelled: "{speed} milliliters per minute",
+    rate_per_hour_abbreviated: "{speed} ml/h",
+    rate_per_hour_spelled: "{speed} milliliters per hour",
+    infusion: "{rate} for {duration}",
+    continuous_infusion: "{rate} since {since} until further notice",
+    continuous_infusion_until: "{rate} since {since} until {end}",
+    date_time_format: "%Y-%m-%d %H:%M",
+    date_format: "%Y-%m-%d",
+    recurring: "{dosage}, {recurrence}",
+    every_n_days_one: "every day from {start}",
+    every_n_days_other: "every {count} days from {start}",
+    on_weekdays: "on {weekdays}",
+    weekdays: ["Mondays", "Tuesdays", "Wednesdays", "Thursdays", "Fridays", "Saturdays", "Sundays"],
+    cycle: "in cycles of {on} on and {off} off from {start}",
+    days_one: "{count} day",
+    days_other: "{count} days",
+    tapering: "from {start}: {phases}",
+    tapering_phase: "{dosage} for {days}",
+    tapering_separator: ", then ",
+    loading_dose: "Bolus {bolus}, then {maintenance}",
+    sliding_scale: "sliding scale by {measurement}:",
+    scale_dose_header: "dose",
+    range_below: "under {to}",
+    range_between: "{from} to under {to}",
+    range_above: "{from} and above",
+    range_any: "any value",
+    mass_abbreviated: "{mg} mg",
+    mass_spelled: "{mg} milligrams",
+    concentration_abbreviated: "{mg} mg/ml",
+    concentration_spelled: "{mg} milligrams per milliliter",
+    international_units_abbreviated: "{count} IU",
+    international_units_spelled: "{count} international units",
+    intermittent_infusion: "{volume} over {run_time} every {interval}",
+    volume_abbreviated: "{volume} ml",
+    volume_spelled: "{volume} milliliters",
+    hours_abbreviated: "{count}h",
+    minutes_abbreviated: "{count}min",
+    hours_one: "{count} hour",
+    hours_other: "{count} hours",
+    minutes_one: "{count} minute",
+    minutes_other: "{count} minutes",
+    routes: RouteNames {
+        oral: "oral",
+        sublingual: "sublingual",
+        feeding_tube: "via feeding tube",
+        intravenous: "intravenous",
+        subcutaneous: "subcutaneous",
+        intramuscular: "intramuscular",
+        inhalation: "inhaled",
+        ophthalmic: "ophthalmic",
+        transdermal: "transdermal"
+    },
+    instructions: InstructionNames {
+        with_food: "with food",
+        before_food: "before meals",
+        on_empty_stomach: "on an empty stomach",
+        before_sleep: "before sleep",
+        do_not_crush: "do not crush",
+        dissolve_in_water: "dissolve in water",
+        avoid_alcohol: "avoid alcohol"
+    },
+    medication_details: "{medication} ({details})",
+    puffs_one: "{count} puff",
+    puffs_other: "{count} puffs",
+    drops_one: "{count} drop",
+    drops_other: "{count} drops",
+    eyes: EyeNames {
+        left: "into the left eye",
+        right: "into the right eye",
+        both: "into each eye"
+    },
+    times_per_day_one: "once a day",
+    times_per_day_other: "{count} times a day",
+    eye_drops: "{drops} {eye} {times}",
+    patches_one: "{count} patch",
+    patches_other: "{count} patches",
+    patch: "{patches}, changed every {interval}",
+    plan_header: "Medication plan for {patient}, born {birth_date}",
+    plan_from: "from {start}",
+    plan_from_until: "from {start} until {stop}",
+    plan_paused: "{period}, paused since {since}",
+    plan_discontinued: "{period}, discontinued on {on}"
+};
+
+pub static DE: Catalog = Catalog {
+    decimal_separator: ',',
+    list_separator: ", ",
+    tablets_one: "{count} Tablette",
+    tablets_other: "{count} Tabletten",
+    morning: "{tablets} morgens",
+    midday: "{tablets} mittags",
+    evening: "{tablets} abends",
+    night: "{tablets} nachts",
+    as_needed: "{dose} bei Bedarf bei {indication}, höchstens {max} pro {day}, \
+                mindestens {interval} Abstand",
+    rate_abbreviated: "{speed} ml/min",
+    rate_spelled: "{speed} Milliliter pro Minute",
+    rate_per_hour_abbreviated: "{speed} ml/h",
+    rate_per_hour_spelled: "{speed} Milliliter pro Stunde",
+    infusion: "{rate} für {duration}",
+    continuous_infusion: "{rate} seit {since} bis auf Weiteres",
+    continuous_infusion_until: "{rate} seit {since} bis {end}",
+    date_time_format: "%d.%m.%Y %H:%M",
+    date_format: "%d.%m.%Y",
+    recurring: "{dosage}, {recurrence}",
+    every_n_days_one: "täglich ab {start}",
+    every_n_days_other: "alle {count} Tage ab {start}",
+    on_weekdays: "{weekdays}",
+    weekdays: ["montags", "dienstags", "mittwochs", "donnerstags", "freitags", "samstags", "sonntags"],
+    cycle: "im Zyklus {on} Einnahme und {off} Pause ab {start}",
+    days_one: "{count} Tag",
+    days_other: "{count} Tage",
+    tapering: "ab {start}: {phases}",
+    tapering_phase: "{dosage} für {days}",
+    tapering_separator: ", dann ",
+    loading_dose: "Bolus {bolus}, dann {maintenance}",
+    sliding_scale: "Korrekturschema nach {measurement}:",
+    scale_dose_header: "Dosis",
+    range_below: "unter {to}",
+    range_between: "{from} bis unter {to}",
+    range_above: "ab {from}",
+    range_any: "jeder Wert",
+    mass_abbreviated: "{mg} mg",
+    mass_spelled: "{mg} Milligramm",
+    concentration_abbreviated: "{mg} mg/ml",
+    concentration_spelled: "{mg} Milligramm pro Milliliter",
+    international_units_abbreviated: "{count} I.E.",
+    international_units_spelled: "{count} Internationale Einheiten",
+    intermittent_infusion: "{volume} über {run_time} alle {interval}",
+    volume_abbreviated: "{volume} ml",
+    volume_spelled: "{volume} Milliliter",
+    hours_abbreviated: "{count} h",
+    minutes_abbreviated: "{count} min",
+    hours_one: "{count} Stunde",
+    hours_other: "{count} Stunden",
+    minutes_one: "{count} Minute",
+    minutes_other: "{count} Minuten",
+    routes: RouteNames {
+        oral: "oral",
+        sublingual: "sublingual",
+        feeding_tube: "über Sonde",
+        intravenous: "intravenös",
+        subcutaneous: "subkutan",
+        intramuscular: "intramuskulär",
+        inhalation: "inhalativ",
+        ophthalmic: "am Auge",
+        transdermal: "transdermal"
+    },
+    instructions: InstructionNames {
+        with_food: "zum Essen",
+        before_food: "vor dem Essen",
+        on_empty_stomach: "nüchtern",
+        before_sleep: "vor dem Schlafengehen",
+        do_not_crush: "nicht zerkleinern",
+        dissolve_in_water: "in Wasser auflösen",
+        avoid_alcohol: "keinen Alkohol trinken"
+    },
+    medication_details: "{medication} ({details})",
+    puffs_one: "{count} Hub",
+    puffs_other: "{count} Hübe",
+    drops_one: "{count} Tropfen",
+    drops_other: "{count} Tropfen",
+    eyes: EyeNames {
+        left: "ins linke Auge",
+        right: "ins rechte Auge",
+        both: "in jedes Auge"
+    },
+    times_per_day_one: "einmal täglich",
+    times_per_day_other: "{count}-mal täglich",
+    eye_drops: "{times} {drops} {eye}",
+    patches_one: "{count} Pflaster",
+    patches_other: "{count} Pflaster",
+    patch: "{patches}, Wechsel alle {interval}",
+    plan_header: "Medikationsplan für {patient}, geboren am {birth_date}",
+    plan_from: "ab {start}",
+    plan_from_until: "vom {start} bis {stop}",
+    plan_paused: "{period}, pausiert seit {since}",
+    plan_discontinued: "{period}, abgesetzt am {on}"
+};
+
+impl Catalog {
+    pub fn route(&self, route: Route) -> &'static str {
+        let names = &self.routes;
+        match route {
+            Route::Oral => names.oral,
+            Route::Sublingual => names.sublingual,
+            Route::FeedingTube => names.feeding_tube,
+            Route::Intravenous => names.intravenous,
+            Route::Subcutaneous => names.subcutaneous,
+            Route::Intramuscular => names.intramuscular,
+            Route::Inhalation => names.inhalation,
+            Route::Ophthalmic => names.ophthalmic,
+            Route::Transdermal => names.transdermal
+        }
+    }
+
+    pub fn instruction(&self, instruction: Instruction) -> &'static str {
+        let names = &self.instructions;
+        match instruction {
+            Instruction::WithFood => names.with_food,
+            Instruction::BeforeFood => names.before_food,
+            Instruction::OnEmptyStomach => names.on_empty_stomach,
+            Instruction::BeforeSleep => names.before_sleep,
+            Instruction::DoNotCrush => names.do_not_crush,
+            Instruction::DissolveInWater => names.dissolve_in_water,
+            Instruction::AvoidAlcohol => names.avoid_alcohol
+        }
+    }
+
+    pub fn eye(&self, eye: Eye) -> &'static str {
+        match eye {
+            Eye::Left => self.eyes.left,
+            Eye::Right => self.eyes.right,
+            Eye::Both => self.eyes.both
+        }
+    }
+}
+
+impl Locale {
+    pub fn catalog(self) -> &'static Catalog {
+        match self {
+            Locale::En => &EN,
+            Locale::De => &DE
+        }
+    }
+
+    // Renders a number formatted with `.` as decimal point using the
+    // locale's decimal separator.
+    pub fn localize_number(self, number: &str) -> String {
+        number.replace('.', &self.catalog().decimal_separator.to_string())
+    }
+}
+
+// Replaces `{name}` placeholders in a single pass, so values may
+// contain braces themselves.
+pub fn fill(template: &str, args: &[(&str, &str)]) -> String {
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        result.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        let value = after.find('}').and_then(|close| {
+            let name = &after[..close];
+            args.iter().find(|(n, _)| *n == name).map(|(_, value)| (*value, close))
+        });
+        match value {
+            Some((value, close)) => {
+                result.push_str(value);
+                rest = &after[close + 1..];
+            }
+            None => {
+                result.push('{');
+                rest = after;
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placeholders_are_filled_in_one_pass() {
+        assert_eq!(fill("{a} and {b}", &[("a", "{b}"), ("b", "2")]), "{b} and 2");
+        assert_eq!(fill("{unknown} {a", &[("a", "1")]), "{unknown} {a");
+    }
+
+    // The parser looks routes and instructions up by their English names.
+    #[test]
+    fn route_and_instruction_names_are_distinct() {
+        for catalog in [&EN, &DE] {
+            let mut names: Vec<&str> = Route::ALL.map(|route| catalog.route(route)).to_vec();
+            names.extend(Instruction::ALL.map(|instruction| catalog.instruction(instruction)));
+            let count = names.len();
+            names.sort_unstable();
+            names.dedup();
+            assert_eq!(names.len(), count);
+        }
+    }
+
+    #[test]
+    fn numbers_use_the_decimal_separator() {
+        assert_eq!(Locale::En.localize_number("1.5"), "1.5");
+        assert_eq!(Locale::De.localize_number("1.5"), "1,5");
+    }
+}
@@ -1,35 +1,36 @@
-struct Medication {
-    drug_name: String,
-    dosage: Dosage
-}
+use std::error::Error;
 
-enum Dosage {
-    Tablet { morning: i32, midday: i32, evening: i32 },
-    Infusion { speed: f32, duration: i32 }
-}
+use chrono::NaiveDate;
 
-fn format_dosage(dosage: Dosage) -> String {
-    match dosage {
-        Dosage::Tablet { morning, midday, evening } =>
-            format!("{morning}-{midday}-{evening}"),
-        Dosage::Infusion { speed, duration } =>
-            format!("{speed} ml/min for {duration}h")
-    }
-}
-
-fn format_medication(m: Medication) -> String {
-    format!("{0}: {1}", m.drug_name, format_dosage(m.dosage))
-}
+use rust::format::{DosageFormatter, TabletStyle};
+use rust::json::{medication_from_json, medication_to_json};
+use rust::locale::Locale;
+use rust::medication::{Dosage, Medication};
+use rust::plan::{MedicationPlan, Patient};
+use rust::units::{Concentration, FlowRate, Mass, TabletCount, TimeSpan};
 
-fn main() {
-    let paracetamol = Medication {
-        drug_name: "Paracetamol".into(),
-        dosage: Dosage::Tablet { morning: 1, midday: 0, evening: 2 }
-    };
-    let infliximab = Medication {
-        drug_name: "Infliximab".into(),
-        dosage: Dosage::Infusion { speed: 1.5, duration: 2 }
-    };
-    println!("{}", format_medication(paracetamol));
-    println!("{}", format_medication(infliximab))
+fn main() -> Result<(), Box<dyn Error>> {
+    let paracetamol = Medication::new("Paracetamol",
+        Dosage::tablet(TabletCount::new(1), TabletCount::ZERO,
+                       TabletCount::new(2), TabletCount::ZERO)?
+    ).with_strength(Mass::from_mg("500".parse()?)?);
+    let infliximab = Medication::new("Infliximab",
+        Dosage::infusion(FlowRate::from_ml_per_min("1.5".parse()?)?, TimeSpan::from_hours(2)?)?
+    ).with_concentration(Concentration::from_mg_per_ml("2".parse()?)?);
+    let ramipril: Medication = "Ramipril: 1-0-½-1".parse()?;
+    println!("{paracetamol}");
+    println!("{infliximab}");
+    println!("{}", DosageFormatter::new().tablet_style(TabletStyle::Verbose)
+        .format_medication(&ramipril));
+    let json = medication_to_json(&infliximab);
+    println!("{json}");
+    println!("{}", medication_from_json(&json)?);
+    let today = NaiveDate::from_ymd_opt(2024, 9, 23).ok_or("invalid date")?;
+    let birth_date = NaiveDate::from_ymd_opt(1958, 3, 12).ok_or("invalid date")?;
+    let mut plan = MedicationPlan::new(Patient::new("Erika Mustermann", birth_date)?);
+    plan.add(paracetamol, today, None)?;
+    plan.add(ramipril, today, None)?;
+    println!("{plan}");
+    println!("{}", DosageFormatter::new().locale(Locale::De).format_plan(&plan));
+    Ok(())
 }
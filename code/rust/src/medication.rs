@@ -0,0 +1,510 @@
+use std::fmt;
+
+use chrono::{NaiveDate, NaiveDateTime};
+
+use crate::continuous::{ContinuousInfusion, RateChange};
+use crate::format::DosageFormatter;
+use crate::forms::{Eye, EyeDrops, Inhaler, Injection, Patch};
+use crate::intermittent::IntermittentInfusion;
+use crate::loading::{Bolus, LoadingDose};
+use crate::prn::AsNeeded;
+use crate::recurrence::Recurrence;
+use crate::route::{Instruction, Route};
+use crate::scale::{ScaleRange, SlidingScale};
+use crate::taper::{TaperPhase, Tapering};
+use crate::units::{
+    Concentration, FlowRate, InternationalUnits, Mass, TabletCount, TimeSpan, UnitError, Volume
+};
+
+// The fields are private so that the route can't get out of step with
+// the dosage; see `with_route` and `with_dosage`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Medication {
+    drug_name: String,
+    dosage: Dosage,
+    // Active ingredient per tablet.
+    strength: Option<Mass>,
+    // Active ingredient per ml of solution.
+    concentration: Option<Concentration>,
+    // One of `dosage.routes()`; `None` for dosages that mix routes, such
+    // as a switch from infusions to tablets.
+    route: Option<Route>,
+    instructions: Vec<Instruction>
+}
+
+// Each variant has a type of its own with private fields, so that code
+// outside this crate has to go through the validating constructors
+// such as `Dosage::tablet` and `Dosage::infusion` to build a dosage,
+// and can't make it invalid afterwards.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Dosage {
+    Tablet(Tablet),
+    Infusion(Infusion),
+    IntermittentInfusion(IntermittentInfusion),
+    ContinuousInfusion(ContinuousInfusion),
+    AsNeeded(AsNeeded),
+    Tapering(Tapering),
+    SlidingScale(SlidingScale),
+    LoadingDose(LoadingDose),
+    Injection(Injection),
+    Inhaler(Inhaler),
+    EyeDrops(EyeDrops),
+    Patch(Patch)
+}
+
+// Tablets taken morning, midday, evening and at night, as on the
+// German federal medication plan, on the days given by `recurrence`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tablet {
+    slots: [TabletCount; 4],
+    recurrence: Recurrence
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Infusion {
+    speed: FlowRate,
+    duration: TimeSpan
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DosageError {
+    InvalidSpeed(UnitError),
+    ZeroDuration,
+    ZeroDose,
+    DoseExceedsDailyMaximum,
+    EmptyIndication,
+    ZeroVolume,
+    ZeroInterval,
+    RunTimeExceedsInterval,
+    NoRate,
+    RateChangesOutOfOrder,
+    EndBeforeLastRateChange,
+    NoWeekdays,
+    EmptyCyclePhase,
+    NoPhases,
+    ZeroPhaseDuration,
+    PhaseOutOfRange,
+    PhasesNotContiguous,
+    NestedTapering,
+    EmptyMeasurement,
+    NoRanges,
+    EmptyRange,
+    RangesOverlap,
+    GapBetweenRanges,
+    NestedLoadingDose,
+    IncompatibleRoute,
+    ZeroFrequency
+}
+
+impl fmt::Display for DosageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DosageError::InvalidSpeed(err) =>
+                write!(f, "invalid infusion speed: {err}"),
+            DosageError::ZeroDuration =>
+                write!(f, "infusion duration must be positive"),
+            DosageError::ZeroDose =>
+                write!(f, "dose must be positive"),
+            DosageError::DoseExceedsDailyMaximum =>
+                write!(f, "dose must not exceed the maximum per 24 hours"),
+            DosageError::EmptyIndication =>
+                write!(f, "indication must not be empty"),
+            DosageError::ZeroVolume =>
+                write!(f, "infusion volume must be positive"),
+            DosageError::ZeroInterval =>
+                write!(f, "interval must be positive"),
+            DosageError::RunTimeExceedsInterval =>
+                write!(f, "infusion run time must not exceed the interval"),
+            DosageError::NoRate =>
+                write!(f, "continuous infusion needs an initial rate"),
+            DosageError::RateChangesOutOfOrder =>
+                write!(f, "rate changes must be in chronological order"),
+            DosageError::EndBeforeLastRateChange =>
+                write!(f, "infusion must end after its last rate change"),
+            DosageError::NoWeekdays =>
+                write!(f, "at least one weekday must be given"),
+            DosageError::EmptyCyclePhase =>
+                write!(f, "cycle needs at least one day on and one day off"),
+            DosageError::NoPhases =>
+                write!(f, "tapering regimen needs at least one phase"),
+            DosageError::ZeroPhaseDuration =>
+                write!(f, "tapering phase must last at least one day"),
+            DosageError::PhaseOutOfRange =>
+                write!(f, "tapering phase ends after the last representable date"),
+            DosageError::PhasesNotContiguous =>
+                write!(f, "each tapering phase must start the day after the previous one ends"),
+            DosageError::NestedTapering =>
+                write!(f, "tapering phase must not be a tapering regimen itself"),
+            DosageError::EmptyMeasurement =>
+                write!(f, "measurement must not be empty"),
+            DosageError::NoRanges =>
+                write!(f, "sliding scale needs at least one range"),
+            DosageError::EmptyRange =>
+                write!(f, "range must end above its start"),
+            DosageError::RangesOverlap =>
+                write!(f, "ranges of a sliding scale must not overlap"),
+            DosageError::GapBetweenRanges =>
+                write!(f, "ranges of a sliding scale must not leave gaps"),
+            DosageError::NestedLoadingDose =>
+                write!(f, "maintenance dosage must not have a loading dose itself"),
+            DosageError::IncompatibleRoute =>
+                write!(f, "dosage cannot be given by this route"),
+            DosageError::ZeroFrequency =>
+                write!(f, "dosage must be given at least once a day")
+        }
+    }
+}
+
+impl std::error::Error for DosageError {}
+
+impl Dosage {
+    // See `Tablet::new`.
+    pub fn tablet(morning: TabletCount, midday: TabletCount, evening: TabletCount,
+                  night: TabletCount) -> Result<Dosage, DosageError> {
+        Tablet::new(morning, midday, evening, night).map(Dosage::Tablet)
+    }
+
+    pub fn infusion(speed: FlowRate, duration: TimeSpan) -> Result<Dosage, DosageError> {
+        Infusion::new(speed, duration).map(Dosage::Infusion)
+    }
+
+    pub fn intermittent_infusion(volume: Volume, run_time: TimeSpan, interval: TimeSpan)
+        -> Result<Dosage, DosageError> {
+        IntermittentInfusion::new(volume, run_time, interval).map(Dosage::IntermittentInfusion)
+    }
+
+    pub fn continuous_infusion(rate_changes: Vec<RateChange>, end: Option<NaiveDateTime>)
+        -> Result<Dosage, DosageError> {
+        ContinuousInfusion::new(rate_changes, end).map(Dosage::ContinuousInfusion)
+    }
+
+    pub fn as_needed(dose: TabletCount, max_per_day: TabletCount, min_interval: TimeSpan,
+                     indication: &str) -> Result<Dosage, DosageError> {
+        AsNeeded::new(dose, max_per_day, min_interval, indication).map(Dosage::AsNeeded)
+    }
+
+    pub fn tapering(phases: Vec<TaperPhase>) -> Result<Dosage, DosageError> {
+        Tapering::new(phases).map(Dosage::Tapering)
+    }
+
+    pub fn sliding_scale(measurement: &str, ranges: Vec<ScaleRange>) -> Result<Dosage, DosageError> {
+        SlidingScale::new(measurement, ranges).map(Dosage::SlidingScale)
+    }
+
+    pub fn loading_dose(bolus: Bolus, maintenance: Dosage) -> Result<Dosage, DosageError> {
+        LoadingDose::new(bolus, maintenance).map(Dosage::LoadingDose)
+    }
+
+    pub fn injection(morning: InternationalUnits, midday: InternationalUnits,
+                     evening: InternationalUnits, night: InternationalUnits)
+        -> Result<Dosage, DosageError> {
+        Injection::new(morning, midday, evening, night).map(Dosage::Injection)
+    }
+
+    pub fn inhaler(morning: u32, midday: u32, evening: u32, night: u32)
+        -> Result<Dosage, DosageError> {
+        Inhaler::new(morning, midday, evening, night).map(Dosage::Inhaler)
+    }
+
+    pub fn eye_drops(drops: u32, eye: Eye, times_per_day: u32) -> Result<Dosage, DosageError> {
+        EyeDrops::new(drops, eye, times_per_day).map(Dosage::EyeDrops)
+    }
+
+    pub fn patch(patches: u32, change_interval: TimeSpan) -> Result<Dosage, DosageError> {
+        Patch::new(patches, change_interval).map(Dosage::Patch)
+    }
+
+    // Volume given over the whole infusion, zero for dosages that aren't
+    // infusions.  As intermittent infusions have no end, this is the
+    // volume of a single run for them.  Continuous infusions until further notice have no
+    // total volume.  Tapering regimens sum up the daily volume of each
+    // phase.  A bolus is added to the volume of its maintenance dosage.
+    pub fn total_volume(&self) -> Result<Volume, UnitError> {
+        match self {
+            Dosage::Tablet(_)
+            | Dosage::AsNeeded(_)
+            | Dosage::SlidingScale(_)
+            | Dosage::Injection(_)
+            | Dosage::Inhaler(_)
+            | Dosage::EyeDrops(_)
+            | Dosage::Patch(_) => Ok(Volume::ZERO),
+            Dosage::Infusion(infusion) => infusion.speed.volume_over(infusion.duration),
+            Dosage::IntermittentInfusion(infusion) => Ok(infusion.volume()),
+            Dosage::ContinuousInfusion(infusion) => {
+                let end = infusion.end().ok_or(UnitError::Unbounded)?;
+                infusion.volume_between(infusion.start(), end)
+            }
+            Dosage::Tapering(tapering) => tapering.phases().iter()
+                .try_fold(Volume::ZERO, |total, phase| {
+                    let per_day = phase.dosage.volume_per_day()?.ml();
+                    total.checked_add(Volume::from_ml(per_day.checked_mul_int(phase.days.into())?)?)
+                }),
+            Dosage::LoadingDose(loading) =>
+                loading.maintenance().total_volume()?.checked_add(loading.bolus().volume())
+        }
+    }
+
+    // Volume given within 24 hours.  For continuous infusions, this is
+    // the volume at the latest rate.  For intermittent infusions whose
+    // interval doesn't divide a day, this is the average over many days,
+    // rounded to the precision of `Decimal`.  Tapering regimens give the
+    // largest volume of any phase.  With a loading dose, this is the
+    // volume of the first day, bolus included.
+    pub fn volume_per_day(&self) -> Result<Volume, UnitError> {
+        match self {
+            Dosage::Tablet(_)
+            | Dosage::AsNeeded(_)
+            | Dosage::SlidingScale(_)
+            | Dosage::Injection(_)
+            | Dosage::Inhaler(_)
+            | Dosage::EyeDrops(_)
+            | Dosage::Patch(_) => Ok(Volume::ZERO),
+            Dosage::Infusion(infusion) =>
+                infusion.speed.volume_over(infusion.duration.min(TimeSpan::DAY)),
+            Dosage::IntermittentInfusion(infusion) => {
+                let per_day = infusion.volume().ml()
+                    .checked_mul_int(TimeSpan::DAY.minutes().into())?
+                    .div_int_rounded(infusion.interval().minutes().into())?;
+                Volume::from_ml(per_day)
+            }
+            Dosage::ContinuousInfusion(infusion) =>
+                infusion.current_rate().rate.volume_over(TimeSpan::DAY),
+            Dosage::Tapering(tapering) => tapering.phases().iter()
+                .try_fold(Volume::ZERO, |max, phase| Ok(max.max(phase.dosage.volume_per_day()?))),
+            Dosage::LoadingDose(loading) =>
+                loading.maintenance().volume_per_day()?.checked_add(loading.bolus().volume())
+        }
+    }
+
+    // Number of tablets taken per day, zero for the other forms.  For
+    // recurring tablet dosages, this counts a day on which a dose is
+    // due.  For as-needed dosages, this is the maximum per 24 hours, and
+    // for tapering regimens the largest number of any phase.  With a
+    // loading dose, this is the number of the first day, bolus included.
+    pub fn tablets_per_day(&self) -> Result<TabletCount, UnitError> {
+        match self {
+            Dosage::Tablet(tablet) => tablet.slots.into_iter()
+                .try_fold(TabletCount::ZERO, TabletCount::checked_add),
+            Dosage::Infusion(_)
+            | Dosage::IntermittentInfusion(_)
+            | Dosage::ContinuousInfusion(_)
+            | Dosage::SlidingScale(_)
+            | Dosage::Injection(_)
+            | Dosage::Inhaler(_)
+            | Dosage::EyeDrops(_)
+            | Dosage::Patch(_) => Ok(TabletCount::ZERO),
+            Dosage::AsNeeded(prn) => Ok(prn.max_per_day()),
+            Dosage::Tapering(tapering) => tapering.phases().iter()
+                .try_fold(TabletCount::ZERO, |max, phase| Ok(max.max(phase.dosage.tablets_per_day()?))),
+            Dosage::LoadingDose(loading) =>
+                loading.maintenance().tablets_per_day()?.checked_add(loading.bolus().tablets())
+        }
+    }
+}
+
+impl Tablet {
+    // Use `TabletCount::ZERO` for `night` in the classic 3-slot
+    // notation.  The tablets are taken daily; see `with_recurrence` for
+    // other schedules.
+    pub fn new(morning: TabletCount, midday: TabletCount, evening: TabletCount,
+               night: TabletCount) -> Result<Tablet, DosageError> {
+        let slots = [morning, midday, evening, night];
+        check_slots(&slots, |count| count.is_zero())?;
+        Ok(Tablet { slots, recurrence: Recurrence::Daily })
+    }
+
+    // Morning, midday, evening and night.
+    pub fn slots(self) -> [TabletCount; 4] {
+        self.slots
+    }
+
+    pub fn recurrence(self) -> Recurrence {
+        self.recurrence
+    }
+
+    // The same tablets taken on the days given by `recurrence` instead.
+    pub fn with_recurrence(self, recurrence: Recurrence) -> Tablet {
+        Tablet { recurrence, ..self }
+    }
+
+    pub fn is_due(self, date: NaiveDate) -> bool {
+        self.recurrence.is_due(date)
+    }
+}
+
+// The rule shared by the slot-based forms, tablets, injections and
+// inhalers: at least one slot must be taken.
+pub(crate) fn check_slots<T>(slots: &[T; 4], is_zero: impl Fn(&T) -> bool)
+    -> Result<(), DosageError> {
+    if slots.iter().all(is_zero) {
+        Err(DosageError::ZeroDose)
+    } else {
+        Ok(())
+    }
+}
+
+impl Infusion {
+    pub fn new(speed: FlowRate, duration: TimeSpan) -> Result<Infusion, DosageError> {
+        if duration.is_zero() {
+            Err(DosageError::ZeroDuration)
+        } else {
+            Ok(Infusion { speed, duration })
+        }
+    }
+
+    pub fn speed(self) -> FlowRate {
+        self.speed
+    }
+
+    pub fn duration(self) -> TimeSpan {
+        self.duration
+    }
+}
+
+impl From<Tablet> for Dosage {
+    fn from(tablet: Tablet) -> Self {
+        Dosage::Tablet(tablet)
+    }
+}
+
+impl From<Infusion> for Dosage {
+    fn from(infusion: Infusion) -> Self {
+        Dosage::Infusion(infusion)
+    }
+}
+
+impl Medication {
+    // The medication gets the dosage's default route and no
+    // instructions.
+    pub fn new(drug_name: &str, dosage: Dosage) -> Medication {
+        Medication {
+            drug_name: drug_name.to_string(),
+            route: dosage.default_route(),
+            dosage,
+            strength: None,
+            concentration: None,
+            instructions: Vec::new()
+        }
+    }
+
+    pub fn with_strength(self, strength: Mass) -> Medication {
+        Medication { strength: Some(strength), ..self }
+    }
+
+    pub fn with_concentration(self, concentration: Concentration) -> Medication {
+        Medication { concentration: Some(concentration), ..self }
+    }
+
+    pub fn with_route(self, route: Route) -> Result<Medication, DosageError> {
+        if self.dosage.allows_route(route) {
+            Ok(Medication { route: Some(route), ..self })
+        } else {
+            Err(DosageError::IncompatibleRoute)
+        }
+    }
+
+    // Replaces the dosage.  A route other than the old dosage's default
+    // is kept if the new dosage allows it; the default route is replaced
+    // by the new dosage's default.
+    pub fn with_dosage(self, dosage: Dosage) -> Result<Medication, DosageError> {
+        let route = match self.route {
+            route if route == self.dosage.default_route() => dosage.default_route(),
+            Some(route) if !dosage.allows_route(route) =>
+                return Err(DosageError::IncompatibleRoute),
+            route => route
+        };
+        Ok(Medication { dosage, route, ..self })
+    }
+
+    // Instructions are kept in the order they are added, each only once.
+    pub fn with_instruction(mut self, instruction: Instruction) -> Medication {
+        if !self.instructions.contains(&instruction) {
+            self.instructions.push(instruction);
+        }
+        self
+    }
+
+    pub fn drug_name(&self) -> &str {
+        &self.drug_name
+    }
+
+    pub fn dosage(&self) -> &Dosage {
+        &self.dosage
+    }
+
+    pub fn strength(&self) -> Option<Mass> {
+        self.strength
+    }
+
+    pub fn concentration(&self) -> Option<Concentration> {
+        self.concentration
+    }
+
+    pub fn route(&self) -> Option<Route> {
+        self.route
+    }
+
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.instructions
+    }
+
+    pub fn total_volume(&self) -> Result<Volume, UnitError> {
+        self.dosage.total_volume()
+    }
+
+    pub fn volume_per_day(&self) -> Result<Volume, UnitError> {
+        self.dosage.volume_per_day()
+    }
+
+    pub fn tablets_per_day(&self) -> Result<TabletCount, UnitError> {
+        self.dosage.tablets_per_day()
+    }
+}
+
+pub fn format_dosage(dosage: &Dosage) -> String {
+    DosageFormatter::new().format_dosage(dosage)
+}
+
+pub fn format_medication(m: &Medication) -> String {
+    DosageFormatter::new().format_medication(m)
+}
+
+impl fmt::Display for Dosage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&format_dosage(self))
+    }
+}
+
+impl fmt::Display for Medication {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&format_medication(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decimal::Decimal;
+
+    fn tablets() -> Dosage {
+        Dosage::tablet(TabletCount::new(1), TabletCount::ZERO, TabletCount::ZERO, TabletCount::ZERO)
+            .unwrap()
+    }
+
+    fn infusion() -> Dosage {
+        let speed = FlowRate::from_ml_per_min(Decimal::from_units(1500)).unwrap();
+        Dosage::infusion(speed, TimeSpan::from_minutes(30)).unwrap()
+    }
+
+    #[test]
+    fn new_dosage_must_allow_a_route_other_than_the_default() {
+        let m = Medication::new("Ramipril", tablets()).with_route(Route::FeedingTube).unwrap();
+        assert_eq!(m.with_dosage(infusion()), Err(DosageError::IncompatibleRoute));
+    }
+
+    #[test]
+    fn default_route_follows_the_dosage() {
+        let m = Medication::new("Paracetamol", tablets()).with_dosage(infusion()).unwrap();
+        assert_eq!(m.route(), Some(Route::Intravenous));
+    }
+}
@@ -0,0 +1,481 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::decimal::{Decimal, DecimalError};
+use crate::locale::EN;
+use crate::medication::{Dosage, DosageError, Medication};
+use crate::route::{Instruction, Route};
+use crate::units::{Concentration, FlowRate, Mass, TabletCount, TimeSpan};
+
+// Parser for the notation produced by `format_dosage` and
+// `format_medication`:
+//
+//   1-0-2
+//   1-0-½-1        (also 1-0-1/2-1)
+//   1.5 ml/min for 2h
+//   1.5 ml/min for 1h 30min
+//   1 ml/h for 24h
+//   Paracetamol: 1-0-2
+//   Paracetamol 500 mg (oral, with food): 1-0-2
+//   Morphine 1 mg/ml (subcutaneous): 1 ml/h for 24h
+//   "Aspirin 100 mg" 100 mg (oral): 1-0-0
+//
+// Routes and instructions are the English names from `locale::EN`.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseErrorKind {
+    Expected(&'static str),
+    ExpectedLiteral(&'static str),
+    NumberOutOfRange,
+    InvalidDecimal(DecimalError),
+    TrailingInput,
+    InvalidDosage(DosageError)
+}
+
+// `position` is the byte offset into the parsed string.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub position: usize,
+    pub kind: ParseErrorKind
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "at position {}: ", self.position)?;
+        match &self.kind {
+            ParseErrorKind::Expected(what) => write!(f, "expected {what}"),
+            ParseErrorKind::ExpectedLiteral(literal) => write!(f, "expected `{literal}`"),
+            ParseErrorKind::NumberOutOfRange => write!(f, "number out of range"),
+            ParseErrorKind::InvalidDecimal(err) => write!(f, "{err}"),
+            ParseErrorKind::TrailingInput => write!(f, "unexpected trailing input"),
+            ParseErrorKind::InvalidDosage(err) => write!(f, "{err}")
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+pub fn parse_dosage(s: &str) -> Result<Dosage, ParseError> {
+    let mut p = Parser { input: s, pos: 0 };
+    p.skip_whitespace();
+    let dosage = p.dosage()?;
+    p.skip_whitespace();
+    p.end()?;
+    Ok(dosage)
+}
+
+pub fn parse_medication(s: &str) -> Result<Medication, ParseError> {
+    let sep = s.rfind(':').ok_or(ParseError {
+        position: s.len(),
+        kind: ParseErrorKind::ExpectedLiteral(":")
+    })?;
+    let offset = sep + 1;
+    let dosage = parse_dosage(&s[offset..])
+        .map_err(|e| ParseError { position: e.position + offset, ..e })?;
+
+    // The head is read from the end, as the drug name may contain
+    // anything: `Name 500 mg, 2 mg/ml (route, instructions)`.
+    let mut head = s[..sep].trim_end();
+    let mut details = None;
+    if let Some(open) = head.strip_suffix(')').and_then(|h| h.rfind('(')) {
+        details = Some((open + 1, &head[open + 1..head.len() - 1]));
+        head = head[..open].trim_end();
+    }
+    let mut concentration = None;
+    if let Some((rest, start, number)) = amount_suffix(head, " mg/ml") {
+        concentration = Some(Concentration::from_mg_per_ml(amount(start, number)?)
+            .map_err(|_| ParseError { position: start, kind: ParseErrorKind::NumberOutOfRange })?);
+        head = rest;
+        if let Some(rest) = head.strip_suffix(',') {
+            head = rest;
+            if amount_suffix(head, " mg").is_none() {
+                return Err(ParseError {
+                    position: head.len(), kind: ParseErrorKind::Expected("strength")
+                });
+            }
+        }
+    }
+    let mut strength = None;
+    if let Some((rest, start, number)) = amount_suffix(head, " mg") {
+        strength = Some(Mass::from_mg(amount(start, number)?)
+            .map_err(|_| ParseError { position: start, kind: ParseErrorKind::NumberOutOfRange })?);
+        head = rest;
+    }
+    let head = head.trim();
+    let drug_name = head.strip_prefix('"').and_then(|h| h.strip_suffix('"')).unwrap_or(head);
+    if drug_name.is_empty() {
+        return Err(ParseError { position: 0, kind: ParseErrorKind::Expected("drug name") });
+    }
+
+    let mut m = Medication::new(drug_name, dosage);
+    if let Some(strength) = strength {
+        m = m.with_strength(strength);
+    }
+    if let Some(concentration) = concentration {
+        m = m.with_concentration(concentration);
+    }
+    if let Some((mut position, details)) = details {
+        for detail in details.split(EN.list_separator) {
+            let route = Route::ALL.into_iter().find(|&route| EN.route(route) == detail);
+            let instruction = Instruction::ALL.into_iter()
+                .find(|&instruction| EN.instruction(instruction) == detail);
+            m = match (route, instruction) {
+                (Some(route), _) => m.with_route(route).map_err(|err| ParseError {
+                    position, kind: ParseErrorKind::InvalidDosage(err)
+                })?,
+                (None, Some(instruction)) => m.with_instruction(instruction),
+                (None, None) => return Err(ParseError {
+                    position, kind: ParseErrorKind::Expected("route or instruction")
+                })
+            };
+            position += detail.len() + EN.list_separator.len();
+        }
+    }
+    Ok(m)
+}
+
+// Whether `format_medication` has to quote `name` for `parse_medication`
+// to read it back: names that could be taken for an amount or details,
+// or that are quoted themselves.
+pub(crate) fn needs_quotes(name: &str) -> bool {
+    name != name.trim()
+        || name.starts_with('"')
+        || name.ends_with([')', ','])
+        || amount_suffix(name, " mg").is_some()
+        || amount_suffix(name, " mg/ml").is_some()
+}
+
+// `head` without an amount such as ` 500 mg` at its end, the position
+// of the amount's number and the number itself.
+fn amount_suffix<'a>(head: &'a str, unit: &str) -> Option<(&'a str, usize, &'a str)> {
+    let rest = head.strip_suffix(unit)?;
+    let start = rest.rfind(' ')? + 1;
+    let number = &rest[start..];
+    number.starts_with(|c: char| c.is_ascii_digit())
+        .then(|| (rest[..start].trim_end(), start, number))
+}
+
+fn amount(position: usize, number: &str) -> Result<Decimal, ParseError> {
+    number.parse().map_err(|err| ParseError { position, kind: ParseErrorKind::InvalidDecimal(err) })
+}
+
+impl FromStr for Dosage {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, ParseError> {
+        parse_dosage(s)
+    }
+}
+
+impl FromStr for Medication {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, ParseError> {
+        parse_medication(s)
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize
+}
+
+impl<'a> Parser<'a> {
+    fn dosage(&mut self) -> Result<Dosage, ParseError> {
+        let start = self.pos;
+        if self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.digits()?;
+            let is_infusion = matches!(self.peek(), Some(c) if c == '.' || c.is_whitespace());
+            self.pos = start;
+            if is_infusion {
+                return self.infusion();
+            }
+        }
+        self.tablet()
+    }
+
+    fn tablet(&mut self) -> Result<Dosage, ParseError> {
+        let start = self.pos;
+        let morning = self.tablet_count()?;
+        self.expect("-")?;
+        let midday = self.tablet_count()?;
+        self.expect("-")?;
+        let evening = self.tablet_count()?;
+        let night = if self.peek() == Some('-') {
+            self.pos += 1;
+            self.tablet_count()?
+        } else {
+            TabletCount::ZERO
+        };
+        self.dosage_at(start, Dosage::tablet(morning, midday, evening, night))
+    }
+
+    // 2, ½, 1¾, 1/2
+    fn tablet_count(&mut self) -> Result<TabletCount, ParseError> {
+        let start = self.pos;
+        let has_digits = self.peek().is_some_and(|c| c.is_ascii_digit());
+        let whole: u32 = if has_digits { self.integer()? } else { 0 };
+        let quarters = match self.peek() {
+            Some('¼') => 1,
+            Some('½') => 2,
+            Some('¾') => 3,
+            Some('/') if has_digits => {
+                self.pos += 1;
+                let denominator: u32 = self.integer()?;
+                return match (whole, denominator) {
+                    (1, 4) => Ok(TabletCount::QUARTER),
+                    (1, 2) => Ok(TabletCount::HALF),
+                    (3, 4) => Ok(TabletCount::from_quarters(3)),
+                    _ => Err(self.error_at(start, ParseErrorKind::Expected("1/4, 1/2 or 3/4")))
+                };
+            }
+            _ if has_digits => return Ok(TabletCount::new(whole)),
+            _ => return Err(self.error(ParseErrorKind::Expected("tablet count")))
+        };
+        self.pos += '½'.len_utf8();
+        Ok(TabletCount::from_quarters(u64::from(whole) * 4 + quarters))
+    }
+
+    fn infusion(&mut self) -> Result<Dosage, ParseError> {
+        let start = self.pos;
+        let number = self.number()?;
+        let speed: Decimal = number.parse()
+            .map_err(|err| self.error_at(start, ParseErrorKind::InvalidDecimal(err)))?;
+        self.whitespace()?;
+        let speed = if self.rest().starts_with("ml/h") {
+            self.pos += "ml/h".len();
+            FlowRate::from_ml_per_hour(speed)
+        } else {
+            self.expect("ml/min")?;
+            FlowRate::from_ml_per_min(speed)
+        };
+        let speed = speed.map_err(|err| {
+            self.error_at(start, ParseErrorKind::InvalidDosage(DosageError::InvalidSpeed(err)))
+        })?;
+        self.whitespace()?;
+        self.expect("for")?;
+        self.whitespace()?;
+        let duration = self.time_span()?;
+        self.dosage_at(start, Dosage::infusion(speed, duration))
+    }
+
+    fn dosage_at(&self, start: usize, result: Result<Dosage, DosageError>)
+        -> Result<Dosage, ParseError> {
+        result.map_err(|err| self.error_at(start, ParseErrorKind::InvalidDosage(err)))
+    }
+
+    // 2h, 30min, 1h 30min
+    fn time_span(&mut self) -> Result<TimeSpan, ParseError> {
+        let start = self.pos;
+        let n: u32 = self.integer()?;
+        self.skip_whitespace();
+        if self.rest().starts_with("min") {
+            self.pos += "min".len();
+            return Ok(TimeSpan::from_minutes(n));
+        }
+        self.expect("h")?;
+        let mut minutes = 0;
+        let before_minutes = self.pos;
+        self.skip_whitespace();
+        if self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            minutes = self.integer()?;
+            self.skip_whitespace();
+            self.expect("min")?;
+        } else {
+            self.pos = before_minutes;
+        }
+        TimeSpan::from_hours(n).ok()
+            .and_then(|h| h.minutes().checked_add(minutes))
+            .map(TimeSpan::from_minutes)
+            .ok_or(self.error_at(start, ParseErrorKind::NumberOutOfRange))
+    }
+
+    fn number(&mut self) -> Result<&'a str, ParseError> {
+        let start = self.pos;
+        self.digits()?;
+        if self.peek() == Some('.') {
+            self.pos += 1;
+            self.digits()?;
+        }
+        Ok(&self.input[start..self.pos])
+    }
+
+    fn integer<T: FromStr>(&mut self) -> Result<T, ParseError> {
+        let start = self.pos;
+        self.digits()?;
+        self.input[start..self.pos].parse()
+            .map_err(|_| self.error_at(start, ParseErrorKind::NumberOutOfRange))
+    }
+
+    fn digits(&mut self) -> Result<(), ParseError> {
+        let len = self.rest().find(|c: char| !c.is_ascii_digit()).unwrap_or(self.rest().len());
+        if len == 0 {
+            return Err(self.error(ParseErrorKind::Expected("digit")));
+        }
+        self.pos += len;
+        Ok(())
+    }
+
+    fn expect(&mut self, literal: &'static str) -> Result<(), ParseError> {
+        if self.rest().starts_with(literal) {
+            self.pos += literal.len();
+            Ok(())
+        } else {
+            Err(self.error(ParseErrorKind::ExpectedLiteral(literal)))
+        }
+    }
+
+    fn whitespace(&mut self) -> Result<(), ParseError> {
+        let start = self.pos;
+        self.skip_whitespace();
+        if self.pos == start {
+            Err(self.error(ParseErrorKind::Expected("space")))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn end(&self) -> Result<(), ParseError> {
+        if self.rest().is_empty() {
+            Ok(())
+        } else {
+            Err(self.error(ParseErrorKind::TrailingInput))
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn error(&self, kind: ParseErrorKind) -> ParseError {
+        self.error_at(self.pos, kind)
+    }
+
+    fn error_at(&self, position: usize, kind: ParseErrorKind) -> ParseError {
+        ParseError { position, kind }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ramipril() -> Medication {
+        let half = TabletCount::HALF;
+        let one = TabletCount::new(1);
+        Medication::new("Ramipril", Dosage::tablet(one, TabletCount::ZERO, half, one).unwrap())
+    }
+
+    fn error(s: &str) -> (usize, ParseErrorKind) {
+        let err = parse_medication(s).unwrap_err();
+        (err.position, err.kind)
+    }
+
+    #[test]
+    fn dosages_round_trip_through_display() {
+        for s in ["1-0-2", "1-0-½-1", "¼-0-1¾", "1.5 ml/min for 2h", "2 ml/min for 1h 30min",
+                  "0.5 ml/min for 45min"] {
+            let dosage = parse_dosage(s).unwrap();
+            assert_eq!(dosage.to_string(), s);
+            assert_eq!(parse_dosage(&dosage.to_string()), Ok(dosage));
+        }
+    }
+
+    #[test]
+    fn alternative_notations_are_accepted() {
+        assert_eq!(parse_dosage("1-0-1/2-1"), parse_dosage("1-0-½-1"));
+        assert_eq!(parse_dosage(" 1-0-2 "), parse_dosage("1-0-2"));
+        assert_eq!(parse_dosage("1.5 ml/min for 90min"), parse_dosage("1.5 ml/min for 1h 30min"));
+    }
+
+    #[test]
+    fn errors_are_reported_at_their_position() {
+        assert_eq!(error("Paracetamol 1-0-2"), (17, ParseErrorKind::ExpectedLiteral(":")));
+        assert_eq!(error(": 1-0-2"), (0, ParseErrorKind::Expected("drug name")));
+        assert_eq!(error("Paracetamol: 1-x-2"), (15, ParseErrorKind::Expected("tablet count")));
+        assert_eq!(error("Paracetamol: 1-0-2 daily"), (19, ParseErrorKind::TrailingInput));
+        assert_eq!(error("Paracetamol: 0-0-0"),
+                   (13, ParseErrorKind::InvalidDosage(DosageError::ZeroDose)));
+        assert_eq!(error("Infliximab: 1.5 ml/s for 2h"),
+                   (16, ParseErrorKind::ExpectedLiteral("ml/min")));
+        assert_eq!(error("Infliximab: 1.5 ml/min for 0h"),
+                   (12, ParseErrorKind::InvalidDosage(DosageError::ZeroDuration)));
+    }
+
+    #[test]
+    fn medication_round_trips_through_display() {
+        let mg = |mg| Mass::from_mg(Decimal::from_units(mg)).unwrap();
+        let speed = FlowRate::from_ml_per_min(Decimal::from_units(1500)).unwrap();
+        let infusion = Dosage::infusion(speed, TimeSpan::from_hours(2).unwrap()).unwrap();
+        let concentration = Concentration::from_mg_per_ml(Decimal::from_units(2000)).unwrap();
+        let medications = [
+            ramipril(),
+            ramipril().with_strength(mg(2500)).with_instruction(Instruction::WithFood),
+            ramipril().with_route(Route::FeedingTube).unwrap()
+                .with_instruction(Instruction::DissolveInWater),
+            Medication::new("Infliximab", infusion)
+                .with_strength(mg(100_000))
+                .with_concentration(concentration)
+        ];
+        for m in medications {
+            assert_eq!(parse_medication(&m.to_string()), Ok(m.clone()), "{m}");
+        }
+    }
+
+    #[test]
+    fn names_that_look_like_amounts_or_details_are_quoted() {
+        let tablet = ramipril().dosage().clone();
+        let aspirin = Medication::new("Aspirin 100 mg", tablet.clone());
+        assert_eq!(aspirin.to_string(), "\"Aspirin 100 mg\" (oral): 1-0-½-1");
+        let with_strength = aspirin.clone()
+            .with_strength(Mass::from_mg(Decimal::from_units(100_000)).unwrap());
+        assert_eq!(with_strength.to_string(), "\"Aspirin 100 mg\" 100 mg (oral): 1-0-½-1");
+        let medications = [
+            aspirin,
+            with_strength,
+            Medication::new("Morphine 1 mg/ml", tablet.clone()),
+            Medication::new("Vitamin D (cholecalciferol)", tablet.clone()),
+            Medication::new("Paracetamol,", tablet.clone())
+                .with_concentration(Concentration::from_mg_per_ml(Decimal::from_units(1)).unwrap()),
+            Medication::new("\"Ramipril\"", tablet.clone()),
+            Medication::new("\"", tablet.clone())
+        ];
+        for m in medications {
+            assert_eq!(parse_medication(&m.to_string()), Ok(m.clone()), "{m}");
+        }
+        assert_eq!(ramipril().to_string(), "Ramipril (oral): 1-0-½-1");
+        assert_eq!(Medication::new("Aspirin mg", tablet).to_string(),
+                   "Aspirin mg (oral): 1-0-½-1");
+    }
+
+    #[test]
+    fn unknown_detail_is_reported_at_its_position() {
+        assert_eq!(parse_medication("Ramipril (oral, after lunch): 1-0-½-1"), Err(ParseError {
+            position: 16, kind: ParseErrorKind::Expected("route or instruction")
+        }));
+    }
+
+    #[test]
+    fn incompatible_route_is_reported_at_its_position() {
+        assert_eq!(parse_medication("Ramipril (intravenous): 1-0-½-1"), Err(ParseError {
+            position: 10, kind: ParseErrorKind::InvalidDosage(DosageError::IncompatibleRoute)
+        }));
+    }
+
+    #[test]
+    fn rate_in_ml_per_hour_round_trips() {
+        let dosage = parse_dosage("1 ml/h for 24h").unwrap();
+        let speed = FlowRate::from_ml_per_hour(Decimal::from_units(1000)).unwrap();
+        assert_eq!(dosage, Dosage::infusion(speed, TimeSpan::DAY).unwrap());
+        assert_eq!(dosage.to_string(), "1 ml/h for 24h");
+    }
+}
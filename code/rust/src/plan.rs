@@ -0,0 +1,340 @@
+use std::fmt;
+
+use chrono::NaiveDate;
+
+use crate::format::DosageFormatter;
+use crate::medication::Medication;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Patient {
+    name: String,
+    birth_date: NaiveDate
+}
+
+// Identifies an entry of a plan.  IDs are handed out in ascending order
+// and never reused, not even after an entry is discontinued.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EntryId(u32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryStatus {
+    Active,
+    Paused,
+    Discontinued
+}
+
+// The status and medication of an entry from `from` on, until the next
+// period starts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Period {
+    pub from: NaiveDate,
+    pub status: EntryStatus,
+    pub medication: Medication
+}
+
+// A medication given from `start` up to and including `stop`, or until
+// further notice.  Every change is recorded as a period, so that past
+// days keep the status and medication they had; the first period starts
+// on `start` and the rest follow in chronological order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlanEntry {
+    id: EntryId,
+    start: NaiveDate,
+    stop: Option<NaiveDate>,
+    periods: Vec<Period>
+}
+
+// The medications of one patient, in the order they were added.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MedicationPlan {
+    patient: Patient,
+    entries: Vec<PlanEntry>,
+    next_id: u32
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlanError {
+    EmptyName,
+    UnknownEntry(EntryId),
+    StopBeforeStart,
+    BeforeStart,
+    BeforeLastChange,
+    NotActive,
+    NotPaused,
+    Discontinued,
+    TooManyEntries
+}
+
+impl fmt::Display for PlanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlanError::EmptyName => write!(f, "patient name must not be empty"),
+            PlanError::UnknownEntry(id) => write!(f, "plan has no entry {id}"),
+            PlanError::StopBeforeStart => write!(f, "stop date must not be before the start date"),
+            PlanError::BeforeStart => write!(f, "date must not be before the entry's start"),
+            PlanError::BeforeLastChange =>
+                write!(f, "date must not be before the entry's last change"),
+            PlanError::NotActive => write!(f, "entry is not active"),
+            PlanError::NotPaused => write!(f, "entry is not paused"),
+            PlanError::Discontinued => write!(f, "entry has been discontinued"),
+            PlanError::TooManyEntries => write!(f, "plan has no more entry IDs")
+        }
+    }
+}
+
+impl std::error::Error for PlanError {}
+
+impl fmt::Display for EntryId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+impl Patient {
+    pub fn new(name: &str, birth_date: NaiveDate) -> Result<Patient, PlanError> {
+        let name = name.trim();
+        if name.is_empty() {
+            Err(PlanError::EmptyName)
+        } else {
+            Ok(Patient { name: name.to_string(), birth_date })
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn birth_date(&self) -> NaiveDate {
+        self.birth_date
+    }
+
+    // Age in completed years on `date`.
+    pub fn age_on(&self, date: NaiveDate) -> u32 {
+        date.years_since(self.birth_date).unwrap_or(0)
+    }
+}
+
+impl PlanEntry {
+    pub fn id(&self) -> EntryId {
+        self.id
+    }
+
+    pub fn start(&self) -> NaiveDate {
+        self.start
+    }
+
+    pub fn stop(&self) -> Option<NaiveDate> {
+        self.stop
+    }
+
+    // In chronological order, never empty.
+    pub fn periods(&self) -> &[Period] {
+        &self.periods
+    }
+
+    // The medication as of the latest change.
+    pub fn medication(&self) -> &Medication {
+        &self.current().medication
+    }
+
+    // The status as of the latest change.
+    pub fn status(&self) -> EntryStatus {
+        self.current().status
+    }
+
+    // The day from which the entry has had its current status.
+    pub fn status_since(&self) -> NaiveDate {
+        let status = self.status();
+        self.periods.iter().rev().take_while(|period| period.status == status)
+            .last().map_or(self.start, |period| period.from)
+    }
+
+    // The period in effect on `date`, `None` outside the entry's dates.
+    pub fn period_on(&self, date: NaiveDate) -> Option<&Period> {
+        if self.stop.is_some_and(|stop| date > stop) {
+            return None;
+        }
+        self.periods.iter().rev().find(|period| period.from <= date)
+    }
+
+    // Whether the medication is given on `date`: within the entry's
+    // dates, and neither paused nor discontinued on that day.
+    pub fn is_given_on(&self, date: NaiveDate) -> bool {
+        self.period_on(date).is_some_and(|period| period.status == EntryStatus::Active)
+    }
+
+    fn current(&self) -> &Period {
+        &self.periods[self.periods.len() - 1]
+    }
+
+    // Records a change from `from` on.  A change on the day of the latest
+    // one replaces it.
+    fn change(&mut self, from: NaiveDate, status: EntryStatus, medication: Medication)
+        -> Result<(), PlanError> {
+        if from < self.start {
+            return Err(PlanError::BeforeStart);
+        }
+        if from < self.current().from {
+            return Err(PlanError::BeforeLastChange);
+        }
+        if from == self.current().from {
+            self.periods.pop();
+        }
+        self.periods.push(Period { from, status, medication });
+        Ok(())
+    }
+}
+
+impl MedicationPlan {
+    pub fn new(patient: Patient) -> MedicationPlan {
+        MedicationPlan { patient, entries: Vec::new(), next_id: 1 }
+    }
+
+    pub fn patient(&self) -> &Patient {
+        &self.patient
+    }
+
+    pub fn entries(&self) -> &[PlanEntry] {
+        &self.entries
+    }
+
+    pub fn entry(&self, id: EntryId) -> Option<&PlanEntry> {
+        self.entries.iter().find(|entry| entry.id == id)
+    }
+
+    // The entries whose medication is given on `date`, in plan order.
+    pub fn given_on(&self, date: NaiveDate) -> impl Iterator<Item = &PlanEntry> {
+        self.entries.iter().filter(move |entry| entry.is_given_on(date))
+    }
+
+    pub fn add(&mut self, medication: Medication, start: NaiveDate, stop: Option<NaiveDate>)
+        -> Result<EntryId, PlanError> {
+        if stop.is_some_and(|stop| stop < start) {
+            return Err(PlanError::StopBeforeStart);
+        }
+        let id = EntryId(self.next_id);
+        self.next_id = self.next_id.checked_add(1).ok_or(PlanError::TooManyEntries)?;
+        let periods = vec![Period { from: start, status: EntryStatus::Active, medication }];
+        self.entries.push(PlanEntry { id, start, stop, periods });
+        Ok(id)
+    }
+
+    // Replaces the medication of an entry that hasn't been discontinued
+    // from `from` on, e.g. to change its dosage.
+    pub fn modify(&mut self, id: EntryId, medication: Medication, from: NaiveDate)
+        -> Result<(), PlanError> {
+        let entry = self.entry_mut(id)?;
+        let status = entry.status();
+        if status == EntryStatus::Discontinued {
+            return Err(PlanError::Discontinued);
+        }
+        entry.change(from, status, medication)
+    }
+
+    pub fn pause(&mut self, id: EntryId, since: NaiveDate) -> Result<(), PlanError> {
+        let entry = self.entry_mut(id)?;
+        if entry.status() != EntryStatus::Active {
+            return Err(PlanError::NotActive);
+        }
+        entry.change(since, EntryStatus::Paused, entry.medication().clone())
+    }
+
+    pub fn resume(&mut self, id: EntryId, on: NaiveDate) -> Result<(), PlanError> {
+        let entry = self.entry_mut(id)?;
+        if entry.status() != EntryStatus::Paused {
+            return Err(PlanError::NotPaused);
+        }
+        entry.change(on, EntryStatus::Active, entry.medication().clone())
+    }
+
+    // The medication is no longer given from `on`.  The entry stays in
+    // the plan, so that its ID keeps referring to it.
+    pub fn discontinue(&mut self, id: EntryId, on: NaiveDate) -> Result<(), PlanError> {
+        let entry = self.entry_mut(id)?;
+        if entry.status() == EntryStatus::Discontinued {
+            return Err(PlanError::Discontinued);
+        }
+        entry.change(on, EntryStatus::Discontinued, entry.medication().clone())
+    }
+
+    fn entry_mut(&mut self, id: EntryId) -> Result<&mut PlanEntry, PlanError> {
+        self.entries.iter_mut().find(|entry| entry.id == id).ok_or(PlanError::UnknownEntry(id))
+    }
+}
+
+impl fmt::Display for MedicationPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&DosageFormatter::new().format_plan(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Days, NaiveDate};
+
+    use super::*;
+
+    fn day(n: u64) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 9, 23).unwrap() + Days::new(n)
+    }
+
+    fn plan_with(medication: &str) -> (MedicationPlan, EntryId) {
+        let patient = Patient::new("Erika Mustermann", day(0)).unwrap();
+        let mut plan = MedicationPlan::new(patient);
+        let id = plan.add(medication.parse().unwrap(), day(0), None).unwrap();
+        (plan, id)
+    }
+
+    #[test]
+    fn resuming_keeps_the_pause_in_the_past() {
+        let (mut plan, id) = plan_with("Salbutamol: 1-0-1");
+        plan.pause(id, day(7)).unwrap();
+        plan.resume(id, day(10)).unwrap();
+        let entry = plan.entry(id).unwrap();
+        assert!(entry.is_given_on(day(6)));
+        assert!(!entry.is_given_on(day(8)));
+        assert!(entry.is_given_on(day(10)));
+        assert_eq!(plan.given_on(day(8)).count(), 0);
+    }
+
+    #[test]
+    fn modifying_takes_effect_from_its_date() {
+        let (mut plan, id) = plan_with("Ramipril: 1-0-½-1");
+        plan.modify(id, "Ramipril: 1-0-1".parse().unwrap(), day(5)).unwrap();
+        let entry = plan.entry(id).unwrap();
+        assert_eq!(entry.period_on(day(4)).unwrap().medication.to_string(),
+                   "Ramipril (oral): 1-0-½-1");
+        assert_eq!(entry.period_on(day(5)).unwrap().medication.to_string(),
+                   "Ramipril (oral): 1-0-1");
+    }
+
+    #[test]
+    fn changes_must_not_go_back_in_time() {
+        let (mut plan, id) = plan_with("Ramipril: 1-0-1");
+        plan.pause(id, day(7)).unwrap();
+        assert_eq!(plan.resume(id, day(6)), Err(PlanError::BeforeLastChange));
+        assert_eq!(plan.entry(id).unwrap().status_since(), day(7));
+    }
+
+    #[test]
+    fn patients_need_a_name() {
+        assert_eq!(Patient::new(" ", day(0)), Err(PlanError::EmptyName));
+        let (plan, _) = plan_with("Ramipril: 1-0-1");
+        assert_eq!(plan.patient().name(), "Erika Mustermann");
+        assert_eq!(plan.patient().birth_date(), day(0));
+        assert_eq!(plan.patient().age_on(day(364)), 0);
+        assert_eq!(plan.patient().age_on(day(365)), 1);
+    }
+
+    #[test]
+    fn entry_ids_are_not_reused_or_wrapped() {
+        let (mut plan, first) = plan_with("Ramipril: 1-0-1");
+        plan.discontinue(first, day(1)).unwrap();
+        let second = plan.add("Ramipril: 1-0-0".parse().unwrap(), day(1), None).unwrap();
+        assert!(second > first);
+        plan.next_id = u32::MAX;
+        assert_eq!(plan.add("Ramipril: 1-0-0".parse().unwrap(), day(1), None),
+                   Err(PlanError::TooManyEntries));
+        assert_eq!(plan.entries().len(), 2);
+    }
+}
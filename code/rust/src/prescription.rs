@@ -0,0 +1,427 @@
+use std::fmt;
+use std::marker::PhantomData;
+
+use chrono::NaiveDateTime;
+
+use crate::medication::Medication;
+
+// Lifecycle of a prescription as a typed state machine.  A
+// `Prescription<S>` only offers the transitions that are legal in state
+// `S`:
+//
+//     prescribed -> verified -> dispensed -> active <-> paused
+//
+// and from every state but the last one to discontinued.  Each
+// transition is recorded with its actor, time and reason; the history
+// is in chronological order and starts with the prescription itself.
+// `AnyPrescription` holds a prescription in whatever state it is in.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Status {
+    Prescribed,
+    Verified,
+    Dispensed,
+    Active,
+    Paused,
+    Discontinued
+}
+
+// Markers for the states of `Prescription`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Prescribed;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Verified;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dispensed;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Active;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Paused;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Discontinued;
+
+// Who changes the status of a prescription, when and why.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    actor: String,
+    at: NaiveDateTime,
+    reason: String
+}
+
+// `from` is `None` for the prescription itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transition {
+    pub from: Option<Status>,
+    pub to: Status,
+    pub actor: String,
+    pub at: NaiveDateTime,
+    pub reason: String
+}
+
+/// Transitions that are not legal in a state do not compile:
+///
+/// ```compile_fail
+/// use chrono::NaiveDate;
+/// use rust::medication::{Dosage, Medication};
+/// use rust::prescription::{Change, Prescription};
+/// use rust::units::TabletCount;
+///
+/// let at = NaiveDate::from_ymd_opt(2024, 9, 23).unwrap().and_hms_opt(8, 0, 0).unwrap();
+/// let one = TabletCount::new(1);
+/// let dosage = Dosage::tablet(one, one, one, TabletCount::ZERO).unwrap();
+/// let change = || Change::new("Dr. Weber", at, "pneumonia").unwrap();
+/// let prescribed = Prescription::prescribe(Medication::new("Amoxicillin", dosage), change());
+/// // Only verified and dispensed prescriptions can be given.
+/// let active = prescribed.activate(change());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Prescription<S> {
+    medication: Medication,
+    history: Vec<Transition>,
+    state: PhantomData<S>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnyPrescription {
+    Prescribed(Prescription<Prescribed>),
+    Verified(Prescription<Verified>),
+    Dispensed(Prescription<Dispensed>),
+    Active(Prescription<Active>),
+    Paused(Prescription<Paused>),
+    Discontinued(Prescription<Discontinued>)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrescriptionError {
+    EmptyActor,
+    EmptyReason,
+    BeforeLastTransition
+}
+
+// A transition that was refused, with the prescription unchanged.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rejected<S> {
+    pub prescription: Box<Prescription<S>>,
+    pub error: PrescriptionError
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Status::Prescribed => "prescribed",
+            Status::Verified => "verified",
+            Status::Dispensed => "dispensed",
+            Status::Active => "active",
+            Status::Paused => "paused",
+            Status::Discontinued => "discontinued"
+        })
+    }
+}
+
+impl fmt::Display for PrescriptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PrescriptionError::EmptyActor => "actor must not be empty",
+            PrescriptionError::EmptyReason => "reason must not be empty",
+            PrescriptionError::BeforeLastTransition =>
+                "transition must not be earlier than the previous one"
+        })
+    }
+}
+
+impl std::error::Error for PrescriptionError {}
+
+impl<S> fmt::Display for Rejected<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "transition rejected: {}", self.error)
+    }
+}
+
+impl<S: fmt::Debug> std::error::Error for Rejected<S> {}
+
+// 2024-09-23 08:00: verified -> dispensed by Pharmacist Koch (stocked)
+impl fmt::Display for Transition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.at.format("%Y-%m-%d %H:%M"))?;
+        if let Some(from) = self.from {
+            write!(f, "{from} -> ")?;
+        }
+        write!(f, "{} by {} ({})", self.to, self.actor, self.reason)
+    }
+}
+
+impl Change {
+    pub fn new(actor: &str, at: NaiveDateTime, reason: &str) -> Result<Change, PrescriptionError> {
+        let (actor, reason) = (actor.trim(), reason.trim());
+        if actor.is_empty() {
+            Err(PrescriptionError::EmptyActor)
+        } else if reason.is_empty() {
+            Err(PrescriptionError::EmptyReason)
+        } else {
+            Ok(Change { actor: actor.to_string(), at, reason: reason.to_string() })
+        }
+    }
+}
+
+impl<S> Prescription<S> {
+    pub fn medication(&self) -> &Medication {
+        &self.medication
+    }
+
+    pub fn history(&self) -> &[Transition] {
+        &self.history
+    }
+
+    pub fn status(&self) -> Status {
+        self.history[self.history.len() - 1].to
+    }
+
+    // The status at `at`, `None` before the prescription was made.
+    pub fn status_at(&self, at: NaiveDateTime) -> Option<Status> {
+        self.history.iter().take_while(|t| t.at <= at).last().map(|t| t.to)
+    }
+
+    fn transition<T>(self, to: Status, change: Change) -> Result<Prescription<T>, Rejected<S>> {
+        let error = PrescriptionError::BeforeLastTransition;
+        if change.at < self.history[self.history.len() - 1].at {
+            return Err(Rejected { prescription: Box::new(self), error });
+        }
+        let from = Some(self.status());
+        let Prescription { medication, mut history, .. } = self;
+        let Change { actor, at, reason } = change;
+        history.push(Transition { from, to, actor, at, reason });
+        Ok(Prescription { medication, history, state: PhantomData })
+    }
+}
+
+impl Prescription<Prescribed> {
+    pub fn prescribe(medication: Medication, change: Change) -> Prescription<Prescribed> {
+        let Change { actor, at, reason } = change;
+        let prescribed = Transition { from: None, to: Status::Prescribed, actor, at, reason };
+        Prescription { medication, history: vec![prescribed], state: PhantomData }
+    }
+
+    pub fn verify(self, change: Change) -> Result<Prescription<Verified>, Rejected<Prescribed>> {
+        self.transition(Status::Verified, change)
+    }
+
+    pub fn discontinue(self, change: Change)
+        -> Result<Prescription<Discontinued>, Rejected<Prescribed>> {
+        self.transition(Status::Discontinued, change)
+    }
+}
+
+impl Prescription<Verified> {
+    pub fn dispense(self, change: Change) -> Result<Prescription<Dispensed>, Rejected<Verified>> {
+        self.transition(Status::Dispensed, change)
+    }
+
+    pub fn discontinue(self, change: Change)
+        -> Result<Prescription<Discontinued>, Rejected<Verified>> {
+        self.transition(Status::Discontinued, change)
+    }
+}
+
+impl Prescription<Dispensed> {
+    // The first administration.
+    pub fn activate(self, change: Change) -> Result<Prescription<Active>, Rejected<Dispensed>> {
+        self.transition(Status::Active, change)
+    }
+
+    pub fn discontinue(self, change: Change)
+        -> Result<Prescription<Discontinued>, Rejected<Dispensed>> {
+        self.transition(Status::Discontinued, change)
+    }
+}
+
+impl Prescription<Active> {
+    pub fn pause(self, change: Change) -> Result<Prescription<Paused>, Rejected<Active>> {
+        self.transition(Status::Paused, change)
+    }
+
+    pub fn discontinue(self, change: Change)
+        -> Result<Prescription<Discontinued>, Rejected<Active>> {
+        self.transition(Status::Discontinued, change)
+    }
+}
+
+impl Prescription<Paused> {
+    pub fn resume(self, change: Change) -> Result<Prescription<Active>, Rejected<Paused>> {
+        self.transition(Status::Active, change)
+    }
+
+    pub fn discontinue(self, change: Change)
+        -> Result<Prescription<Discontinued>, Rejected<Paused>> {
+        self.transition(Status::Discontinued, change)
+    }
+}
+
+impl AnyPrescription {
+    pub fn medication(&self) -> &Medication {
+        self.parts().0
+    }
+
+    pub fn history(&self) -> &[Transition] {
+        self.parts().1
+    }
+
+    pub fn status(&self) -> Status {
+        match self {
+            AnyPrescription::Prescribed(_) => Status::Prescribed,
+            AnyPrescription::Verified(_) => Status::Verified,
+            AnyPrescription::Dispensed(_) => Status::Dispensed,
+            AnyPrescription::Active(_) => Status::Active,
+            AnyPrescription::Paused(_) => Status::Paused,
+            AnyPrescription::Discontinued(_) => Status::Discontinued
+        }
+    }
+
+    // The status at `at`, `None` before the prescription was made.
+    pub fn status_at(&self, at: NaiveDateTime) -> Option<Status> {
+        self.history().iter().take_while(|t| t.at <= at).last().map(|t| t.to)
+    }
+
+    fn parts(&self) -> (&Medication, &[Transition]) {
+        match self {
+            AnyPrescription::Prescribed(p) => (&p.medication, &p.history),
+            AnyPrescription::Verified(p) => (&p.medication, &p.history),
+            AnyPrescription::Dispensed(p) => (&p.medication, &p.history),
+            AnyPrescription::Active(p) => (&p.medication, &p.history),
+            AnyPrescription::Paused(p) => (&p.medication, &p.history),
+            AnyPrescription::Discontinued(p) => (&p.medication, &p.history)
+        }
+    }
+}
+
+impl From<Prescription<Prescribed>> for AnyPrescription {
+    fn from(p: Prescription<Prescribed>) -> Self {
+        AnyPrescription::Prescribed(p)
+    }
+}
+
+impl From<Prescription<Verified>> for AnyPrescription {
+    fn from(p: Prescription<Verified>) -> Self {
+        AnyPrescription::Verified(p)
+    }
+}
+
+impl From<Prescription<Dispensed>> for AnyPrescription {
+    fn from(p: Prescription<Dispensed>) -> Self {
+        AnyPrescription::Dispensed(p)
+    }
+}
+
+impl From<Prescription<Active>> for AnyPrescription {
+    fn from(p: Prescription<Active>) -> Self {
+        AnyPrescription::Active(p)
+    }
+}
+
+impl From<Prescription<Paused>> for AnyPrescription {
+    fn from(p: Prescription<Paused>) -> Self {
+        AnyPrescription::Paused(p)
+    }
+}
+
+impl From<Prescription<Discontinued>> for AnyPrescription {
+    fn from(p: Prescription<Discontinued>) -> Self {
+        AnyPrescription::Discontinued(p)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::{NaiveDate, TimeDelta};
+
+    use crate::medication::Dosage;
+    use crate::units::TabletCount;
+
+    fn at(hours: i64) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 9, 23).unwrap().and_hms_opt(8, 0, 0).unwrap()
+            + TimeDelta::hours(hours)
+    }
+
+    fn change(actor: &str, hours: i64, reason: &str) -> Change {
+        Change::new(actor, at(hours), reason).unwrap()
+    }
+
+    fn prescribed() -> Prescription<Prescribed> {
+        let one = TabletCount::new(1);
+        let dosage = Dosage::tablet(one, one, one, TabletCount::ZERO).unwrap();
+        Prescription::prescribe(Medication::new("Amoxicillin", dosage),
+                                change("Dr. Weber", 0, "pneumonia"))
+    }
+
+    fn active() -> Prescription<Active> {
+        prescribed().verify(change("Pharmacist Koch", 2, "no interactions")).unwrap()
+            .dispense(change("Pharmacist Koch", 3, "ward stock")).unwrap()
+            .activate(change("Nurse Berg", 4, "first dose given")).unwrap()
+    }
+
+    #[test]
+    fn changes_need_an_actor_and_a_reason() {
+        assert_eq!(Change::new(" ", at(0), "pneumonia"), Err(PrescriptionError::EmptyActor));
+        assert_eq!(Change::new("", at(0), ""), Err(PrescriptionError::EmptyActor));
+        assert_eq!(Change::new("Dr. Weber", at(0), "\t"), Err(PrescriptionError::EmptyReason));
+        let change = Change::new(" Dr. Weber ", at(0), " pneumonia\n").unwrap();
+        let prescription = Prescription::prescribe(prescribed().medication().clone(), change);
+        assert_eq!(prescription.history()[0].actor, "Dr. Weber");
+        assert_eq!(prescription.history()[0].reason, "pneumonia");
+    }
+
+    #[test]
+    fn history_records_every_transition_in_order() {
+        let discontinued = active()
+            .pause(change("Dr. Weber", 30, "rash")).unwrap()
+            .resume(change("Dr. Weber", 30, "rash gone")).unwrap()
+            .discontinue(change("Dr. Weber", 36, "allergy")).unwrap();
+        let history: Vec<_> = discontinued.history().iter()
+            .map(|t| (t.from, t.to, t.at))
+            .collect();
+        assert_eq!(history, [
+            (None, Status::Prescribed, at(0)),
+            (Some(Status::Prescribed), Status::Verified, at(2)),
+            (Some(Status::Verified), Status::Dispensed, at(3)),
+            (Some(Status::Dispensed), Status::Active, at(4)),
+            (Some(Status::Active), Status::Paused, at(30)),
+            (Some(Status::Paused), Status::Active, at(30)),
+            (Some(Status::Active), Status::Discontinued, at(36))
+        ]);
+        assert_eq!(discontinued.status(), Status::Discontinued);
+        assert_eq!(discontinued.history()[3].to_string(),
+                   "2024-09-23 12:00: dispensed -> active by Nurse Berg (first dose given)");
+        assert_eq!(discontinued.history()[0].to_string(),
+                   "2024-09-23 08:00: prescribed by Dr. Weber (pneumonia)");
+    }
+
+    #[test]
+    fn transitions_before_the_last_one_are_rejected() {
+        let active = active();
+        let rejected = active.clone().pause(change("Dr. Weber", 1, "rash")).unwrap_err();
+        assert_eq!(rejected.error, PrescriptionError::BeforeLastTransition);
+        assert_eq!(*rejected.prescription, active);
+        assert_eq!(rejected.to_string(),
+                   "transition rejected: transition must not be earlier than the previous one");
+        let rejected = prescribed().discontinue(change("Dr. Weber", -1, "mistake")).unwrap_err();
+        assert_eq!(rejected.error, PrescriptionError::BeforeLastTransition);
+        assert_eq!(rejected.prescription.status(), Status::Prescribed);
+    }
+
+    #[test]
+    fn status_at_follows_the_history() {
+        let paused = active().pause(change("Dr. Weber", 30, "rash")).unwrap();
+        assert_eq!(paused.status_at(at(-1)), None);
+        assert_eq!(paused.status_at(at(0)), Some(Status::Prescribed));
+        assert_eq!(paused.status_at(at(3)), Some(Status::Dispensed));
+        assert_eq!(paused.status_at(at(29)), Some(Status::Active));
+        assert_eq!(paused.status_at(at(30)), Some(Status::Paused));
+        let any = AnyPrescription::from(paused);
+        assert_eq!(any.status(), Status::Paused);
+        assert_eq!(any.status_at(at(2)), Some(Status::Verified));
+        assert_eq!(any.status_at(at(100)), Some(Status::Paused));
+        assert_eq!(any.medication().drug_name(), "Amoxicillin");
+        assert_eq!(any.history().len(), 5);
+    }
+}
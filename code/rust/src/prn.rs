@@ -0,0 +1,175 @@
+use chrono::{NaiveDateTime, TimeDelta};
+
+use crate::medication::{Dosage, DosageError};
+use crate::units::{TabletCount, TimeSpan};
+
+// PRN: `dose` tablets when needed for `indication`, at most
+// `max_per_day` tablets in any 24 hours and at least `min_interval`
+// between two doses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsNeeded {
+    dose: TabletCount,
+    max_per_day: TabletCount,
+    min_interval: TimeSpan,
+    indication: String
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrnCheck {
+    Allowed,
+    TooSoon { next_allowed: NaiveDateTime },
+    DailyMaximumReached { next_allowed: NaiveDateTime }
+}
+
+impl AsNeeded {
+    pub fn new(dose: TabletCount, max_per_day: TabletCount, min_interval: TimeSpan,
+               indication: &str) -> Result<AsNeeded, DosageError> {
+        let indication = indication.trim();
+        if dose.is_zero() {
+            Err(DosageError::ZeroDose)
+        } else if min_interval.is_zero() {
+            Err(DosageError::ZeroInterval)
+        } else if dose > max_per_day {
+            Err(DosageError::DoseExceedsDailyMaximum)
+        } else if indication.is_empty() {
+            Err(DosageError::EmptyIndication)
+        } else {
+            Ok(AsNeeded { dose, max_per_day, min_interval, indication: indication.to_string() })
+        }
+    }
+
+    pub fn dose(&self) -> TabletCount {
+        self.dose
+    }
+
+    pub fn max_per_day(&self) -> TabletCount {
+        self.max_per_day
+    }
+
+    pub fn min_interval(&self) -> TimeSpan {
+        self.min_interval
+    }
+
+    pub fn indication(&self) -> &str {
+        &self.indication
+    }
+
+    // Decides whether another dose may be given at `at`.  `previous`
+    // holds the times of earlier administrations of `dose` tablets each,
+    // in any order; those after `at` are ignored.  A next allowed time
+    // past the last representable one is given as `NaiveDateTime::MAX`.
+    pub fn check(&self, previous: &[NaiveDateTime], at: NaiveDateTime) -> PrnCheck {
+        let day = TimeDelta::hours(24);
+        let mut past: Vec<NaiveDateTime> = previous.iter().copied().filter(|&t| t <= at).collect();
+        past.sort();
+
+        let min_interval = TimeDelta::minutes(self.min_interval.minutes().into());
+        let too_soon = past.last().and_then(|&last| match last.checked_add_signed(min_interval) {
+            Some(next) if next <= at => None,
+            next => Some(next.unwrap_or(NaiveDateTime::MAX))
+        });
+
+        let max_doses = usize::try_from(self.max_per_day.quarters() / self.dose.quarters())
+            .unwrap_or(usize::MAX);
+        let window_start = at.checked_sub_signed(day);
+        let in_window = past.iter()
+            .filter(|&&t| window_start.is_none_or(|start| t > start))
+            .count();
+        let limit_reached = (in_window >= max_doses)
+            .then(|| past[past.len() - max_doses].checked_add_signed(day)
+                .unwrap_or(NaiveDateTime::MAX));
+
+        match (too_soon, limit_reached) {
+            (None, None) => PrnCheck::Allowed,
+            (Some(next_allowed), None) => PrnCheck::TooSoon { next_allowed },
+            (Some(interval), Some(limit)) if interval > limit =>
+                PrnCheck::TooSoon { next_allowed: interval },
+            (_, Some(next_allowed)) => PrnCheck::DailyMaximumReached { next_allowed }
+        }
+    }
+}
+
+impl From<AsNeeded> for Dosage {
+    fn from(prn: AsNeeded) -> Self {
+        Dosage::AsNeeded(prn)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::NaiveDate;
+
+    fn ibuprofen() -> AsNeeded {
+        AsNeeded::new(TabletCount::new(1), TabletCount::new(3), TimeSpan::from_minutes(360),
+                      "pain").unwrap()
+    }
+
+    fn at(day: u32, hour: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 3, day).unwrap().and_hms_opt(hour, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn invalid_prn_dosages_are_rejected() {
+        let (one, two, interval) = (TabletCount::new(1), TabletCount::new(2), TimeSpan::DAY);
+        assert_eq!(AsNeeded::new(TabletCount::ZERO, two, interval, "pain"),
+                   Err(DosageError::ZeroDose));
+        assert_eq!(AsNeeded::new(two, one, interval, "pain"),
+                   Err(DosageError::DoseExceedsDailyMaximum));
+        assert_eq!(AsNeeded::new(one, two, interval, "  "), Err(DosageError::EmptyIndication));
+        assert_eq!(AsNeeded::new(one, two, TimeSpan::from_minutes(0), "pain"),
+                   Err(DosageError::ZeroInterval));
+    }
+
+    #[test]
+    fn dose_is_allowed_after_the_minimum_interval() {
+        let prn = ibuprofen();
+        assert_eq!(prn.check(&[], at(1, 8)), PrnCheck::Allowed);
+        assert_eq!(prn.check(&[at(1, 8)], at(1, 14)), PrnCheck::Allowed);
+        // Administrations after `at` are ignored.
+        assert_eq!(prn.check(&[at(1, 12)], at(1, 8)), PrnCheck::Allowed);
+    }
+
+    #[test]
+    fn dose_within_the_minimum_interval_is_too_soon() {
+        assert_eq!(ibuprofen().check(&[at(1, 8)], at(1, 10)),
+                   PrnCheck::TooSoon { next_allowed: at(1, 14) });
+    }
+
+    #[test]
+    fn daily_maximum_counts_the_last_24_hours() {
+        let prn = ibuprofen();
+        let previous = [at(1, 12), at(1, 0), at(1, 6)];
+        assert_eq!(prn.check(&previous, at(1, 18)),
+                   PrnCheck::DailyMaximumReached { next_allowed: at(2, 0) });
+        assert_eq!(prn.check(&previous, at(2, 0)), PrnCheck::Allowed);
+    }
+
+    #[test]
+    fn checks_at_the_limits_of_the_calendar_do_not_overflow() {
+        let prn = ibuprofen();
+        let first = NaiveDateTime::MIN;
+        assert_eq!(prn.check(&[first, first], first), PrnCheck::TooSoon {
+            next_allowed: first + TimeDelta::hours(6)
+        });
+        let last = NaiveDateTime::MAX;
+        assert_eq!(prn.check(&[last], last),
+                   PrnCheck::TooSoon { next_allowed: NaiveDateTime::MAX });
+        assert_eq!(prn.check(&[last, last, last], last),
+                   PrnCheck::DailyMaximumReached { next_allowed: NaiveDateTime::MAX });
+    }
+
+    #[test]
+    fn later_of_both_limits_is_reported() {
+        let prn = ibuprofen();
+        let previous = [at(1, 0), at(1, 6), at(1, 21)];
+        assert_eq!(prn.check(&previous, at(1, 22)),
+                   PrnCheck::TooSoon { next_allowed: at(2, 3) });
+        assert_eq!(prn.check(&previous, at(2, 2)),
+                   PrnCheck::TooSoon { next_allowed: at(2, 3) });
+        let previous = [at(1, 0), at(1, 12), at(1, 18)];
+        assert_eq!(prn.check(&previous, at(1, 23)),
+                   PrnCheck::DailyMaximumReached { next_allowed: at(2, 0) });
+    }
+}
@@ -0,0 +1,113 @@
+use chrono::{Datelike, NaiveDate, WeekdaySet};
+
+use crate::medication::DosageError;
+
+// On which days a tablet dosage is taken.  Every-n-days and cyclic
+// recurrences count from their `start` day, on which a dose is due.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Recurrence {
+    #[default]
+    Daily,
+    #[non_exhaustive]
+    EveryNDays { days: u32, start: NaiveDate },
+    #[non_exhaustive]
+    Weekdays { weekdays: WeekdaySet },
+    // 21 days on, 7 days off
+    #[non_exhaustive]
+    Cycle { days_on: u32, days_off: u32, start: NaiveDate }
+}
+
+impl Recurrence {
+    pub fn every_n_days(days: u32, start: NaiveDate) -> Result<Recurrence, DosageError> {
+        if days == 0 {
+            Err(DosageError::ZeroInterval)
+        } else {
+            Ok(Recurrence::EveryNDays { days, start })
+        }
+    }
+
+    pub fn weekdays(weekdays: WeekdaySet) -> Result<Recurrence, DosageError> {
+        if weekdays.is_empty() {
+            Err(DosageError::NoWeekdays)
+        } else {
+            Ok(Recurrence::Weekdays { weekdays })
+        }
+    }
+
+    pub fn cycle(days_on: u32, days_off: u32, start: NaiveDate) -> Result<Recurrence, DosageError> {
+        if days_on == 0 || days_off == 0 {
+            Err(DosageError::EmptyCyclePhase)
+        } else {
+            Ok(Recurrence::Cycle { days_on, days_off, start })
+        }
+    }
+
+    pub fn is_due(self, date: NaiveDate) -> bool {
+        let days_since = |start: NaiveDate| u64::try_from((date - start).num_days()).ok();
+        match self {
+            Recurrence::Daily => true,
+            Recurrence::EveryNDays { days, start } =>
+                days_since(start).is_some_and(|n| n.is_multiple_of(days.into())),
+            Recurrence::Weekdays { weekdays } => weekdays.contains(date.weekday()),
+            Recurrence::Cycle { days_on, days_off, start } => days_since(start)
+                .and_then(|n| n.checked_rem(u64::from(days_on) + u64::from(days_off)))
+                .is_some_and(|day| day < u64::from(days_on))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::Weekday;
+
+    fn day(n: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 9, 1).unwrap() + chrono::Days::new(u64::from(n - 1))
+    }
+
+    fn due_days(recurrence: Recurrence, days: std::ops::RangeInclusive<u32>) -> Vec<u32> {
+        days.filter(|&n| recurrence.is_due(day(n))).collect()
+    }
+
+    #[test]
+    fn invalid_recurrences_are_rejected() {
+        assert_eq!(Recurrence::every_n_days(0, day(1)), Err(DosageError::ZeroInterval));
+        assert_eq!(Recurrence::weekdays(WeekdaySet::EMPTY), Err(DosageError::NoWeekdays));
+        assert_eq!(Recurrence::cycle(0, 7, day(1)), Err(DosageError::EmptyCyclePhase));
+        assert_eq!(Recurrence::cycle(21, 0, day(1)), Err(DosageError::EmptyCyclePhase));
+    }
+
+    #[test]
+    fn daily_dosages_are_always_due() {
+        assert_eq!(due_days(Recurrence::Daily, 1..=3), [1, 2, 3]);
+    }
+
+    #[test]
+    fn every_n_days_counts_from_the_start() {
+        let every_other_day = Recurrence::every_n_days(2, day(3)).unwrap();
+        assert_eq!(due_days(every_other_day, 1..=8), [3, 5, 7]);
+        let weekly = Recurrence::every_n_days(7, day(2)).unwrap();
+        assert_eq!(due_days(weekly, 1..=30), [2, 9, 16, 23, 30]);
+    }
+
+    #[test]
+    fn weekdays_are_due_on_their_days() {
+        // 2024-09-02 is a Monday.
+        let weekdays = [Weekday::Mon, Weekday::Wed, Weekday::Fri].into_iter().collect();
+        assert_eq!(due_days(Recurrence::weekdays(weekdays).unwrap(), 1..=10), [2, 4, 6, 9]);
+    }
+
+    #[test]
+    fn cycles_alternate_days_on_and_off() {
+        let cycle = Recurrence::cycle(3, 2, day(2)).unwrap();
+        assert_eq!(due_days(cycle, 1..=13), [2, 3, 4, 7, 8, 9, 12, 13]);
+    }
+
+    #[test]
+    fn nothing_is_due_before_the_start() {
+        assert!(!Recurrence::every_n_days(1, day(5)).unwrap().is_due(day(4)));
+        assert!(!Recurrence::cycle(21, 7, day(5)).unwrap().is_due(day(4)));
+        assert!(!Recurrence::cycle(21, 7, day(5)).unwrap().is_due(NaiveDate::MIN));
+    }
+}
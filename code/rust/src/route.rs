@@ -0,0 +1,93 @@
+use crate::loading::Bolus;
+use crate::medication::Dosage;
+
+// How a medication is administered.  Tablets are oral and infusions
+// intravenous unless the medication says otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Route {
+    Oral,
+    Sublingual,
+    // Tablets dissolved and given through a feeding tube.
+    FeedingTube,
+    Intravenous,
+    Subcutaneous,
+    Intramuscular,
+    Inhalation,
+    Ophthalmic,
+    Transdermal
+}
+
+// Structured instructions for taking a medication, as printed on the
+// medication plan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Instruction {
+    WithFood,
+    BeforeFood,
+    OnEmptyStomach,
+    BeforeSleep,
+    DoNotCrush,
+    DissolveInWater,
+    AvoidAlcohol
+}
+
+const TABLET_ROUTES: &[Route] = &[Route::Oral, Route::Sublingual, Route::FeedingTube];
+const INFUSION_ROUTES: &[Route] = &[Route::Intravenous, Route::Subcutaneous];
+const INJECTION_ROUTES: &[Route] = &[Route::Subcutaneous, Route::Intravenous, Route::Intramuscular];
+
+impl Route {
+    pub const ALL: [Route; 9] = [
+        Route::Oral, Route::Sublingual, Route::FeedingTube, Route::Intravenous,
+        Route::Subcutaneous, Route::Intramuscular, Route::Inhalation, Route::Ophthalmic,
+        Route::Transdermal
+    ];
+}
+
+impl Instruction {
+    pub const ALL: [Instruction; 7] = [
+        Instruction::WithFood, Instruction::BeforeFood, Instruction::OnEmptyStomach,
+        Instruction::BeforeSleep, Instruction::DoNotCrush, Instruction::DissolveInWater,
+        Instruction::AvoidAlcohol
+    ];
+}
+
+impl Dosage {
+    // The routes the dosage can be given by, the usual one first.
+    // Tapering regimens use the routes of their first phase, which the
+    // other phases must allow as well.
+    pub fn routes(&self) -> Vec<Route> {
+        match self {
+            Dosage::Tablet(_) | Dosage::AsNeeded(_) => TABLET_ROUTES.to_vec(),
+            Dosage::Infusion(_)
+            | Dosage::IntermittentInfusion(_)
+            | Dosage::ContinuousInfusion(_) => INFUSION_ROUTES.to_vec(),
+            Dosage::SlidingScale(_) | Dosage::Injection(_) => INJECTION_ROUTES.to_vec(),
+            Dosage::Inhaler(_) => vec![Route::Inhalation],
+            Dosage::EyeDrops(_) => vec![Route::Ophthalmic],
+            Dosage::Patch(_) => vec![Route::Transdermal],
+            Dosage::Tapering(tapering) => {
+                let phases = tapering.phases();
+                phases[0].dosage.routes().into_iter()
+                    .filter(|&route| phases.iter().all(|phase| phase.dosage.allows_route(route)))
+                    .collect()
+            }
+            Dosage::LoadingDose(loading) => {
+                let bolus_routes = match loading.bolus() {
+                    Bolus::Volume(_) => INFUSION_ROUTES,
+                    Bolus::Tablets(_) => TABLET_ROUTES
+                };
+                loading.maintenance().routes().into_iter()
+                    .filter(|route| bolus_routes.contains(route))
+                    .collect()
+            }
+        }
+    }
+
+    pub fn allows_route(&self, route: Route) -> bool {
+        self.routes().contains(&route)
+    }
+
+    // The route a medication with this dosage gets by default.
+    pub fn default_route(&self) -> Option<Route> {
+        self.routes().first().copied()
+    }
+}
@@ -0,0 +1,486 @@
+use std::fmt;
+
+use crate::decimal::{Decimal, DecimalError};
+use crate::medication::{Dosage, DosageError, Medication};
+use crate::recurrence::Recurrence;
+use crate::units::{FlowRate, TabletCount, TimeSpan};
+
+// Row of the nullable table from the article, extended by a `night`
+// column:
+//
+// CREATE TABLE medications(
+//     drugName VARCHAR(255) NOT NULL,
+//     dosageKind int NOT NULL, -- 1 for tablet, 2 for infusion
+//     morning int,
+//     midday int,
+//     evening int,
+//     night int,
+//     speed double,
+//     duration int)
+//
+// Tablet counts are whole tablets, `speed` is in ml/min, `duration` in
+// whole hours.  Tables without the `night` column map it to null,
+// which means no tablets at night.  Tablets are always taken daily, and
+// there is no room for the strength or concentration of a medication.
+
+pub const DOSAGE_KIND_TABLET: i32 = 1;
+pub const DOSAGE_KIND_INFUSION: i32 = 2;
+pub const DRUG_NAME_MAX_LEN: usize = 255;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MedicationRow {
+    pub drug_name: String,
+    pub dosage_kind: i32,
+    pub morning: Option<i32>,
+    pub midday: Option<i32>,
+    pub evening: Option<i32>,
+    pub night: Option<i32>,
+    pub speed: Option<f64>,
+    pub duration: Option<i32>
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    DrugName,
+    DosageKind,
+    Morning,
+    Midday,
+    Evening,
+    Night,
+    Speed,
+    Duration,
+    // Not columns of the table, but fields of `Medication` it can't hold.
+    Strength,
+    Concentration,
+    Route,
+    Instructions
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    TooLong,
+    UnknownDosageKind,
+    MustBeNull,
+    MustNotBeNull,
+    OutOfRange,
+    Negative,
+    NotWholeTablets,
+    NotWholeHours,
+    NotRepresentable,
+    TooPrecise,
+    NoColumn,
+    InvalidDosage(DosageError)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Violation {
+    pub column: Column,
+    pub rule: Rule
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RowError {
+    pub violations: Vec<Violation>
+}
+
+impl fmt::Display for Column {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Column::DrugName => "drugName",
+            Column::DosageKind => "dosageKind",
+            Column::Morning => "morning",
+            Column::Midday => "midday",
+            Column::Evening => "evening",
+            Column::Night => "night",
+            Column::Speed => "speed",
+            Column::Duration => "duration",
+            Column::Strength => "strength",
+            Column::Concentration => "concentration",
+            Column::Route => "route",
+            Column::Instructions => "instructions"
+        })
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let column = self.column;
+        match self.rule {
+            Rule::TooLong =>
+                write!(f, "{column} is longer than {DRUG_NAME_MAX_LEN} characters"),
+            Rule::UnknownDosageKind =>
+                write!(f, "{column} must be {DOSAGE_KIND_TABLET} or {DOSAGE_KIND_INFUSION}"),
+            Rule::MustBeNull =>
+                write!(f, "{column} must be null for this dosageKind"),
+            Rule::MustNotBeNull =>
+                write!(f, "{column} must not be null for this dosageKind"),
+            Rule::OutOfRange =>
+                write!(f, "{column} is out of range"),
+            Rule::Negative =>
+                write!(f, "{column} must not be negative"),
+            Rule::NotWholeTablets =>
+                write!(f, "{column} must be a whole number of tablets"),
+            Rule::NotRepresentable =>
+                write!(f, "{column} cannot represent this kind of dosage"),
+            Rule::TooPrecise =>
+                write!(f, "{column} has more than {} decimal places", crate::decimal::SCALE),
+            Rule::NotWholeHours =>
+                write!(f, "{column} must be a whole number of hours"),
+            Rule::NoColumn =>
+                write!(f, "{column} cannot be stored in the table"),
+            Rule::InvalidDosage(err) =>
+                write!(f, "{column}: {err}")
+        }
+    }
+}
+
+impl fmt::Display for RowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid medications row: ")?;
+        for (i, v) in self.violations.iter().enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{v}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for RowError {}
+
+impl TryFrom<&Medication> for MedicationRow {
+    type Error = RowError;
+
+    fn try_from(m: &Medication) -> Result<Self, RowError> {
+        let mut violations = Vec::new();
+        if m.drug_name().chars().count() > DRUG_NAME_MAX_LEN {
+            violations.push(Violation { column: Column::DrugName, rule: Rule::TooLong });
+        }
+        violations.extend([
+            (Column::Strength, m.strength().is_some()),
+            (Column::Concentration, m.concentration().is_some()),
+            // The dosage kind implies the default route.
+            (Column::Route, m.route() != m.dosage().default_route()),
+            (Column::Instructions, !m.instructions().is_empty())
+        ].into_iter()
+            .filter(|&(_, present)| present)
+            .map(|(column, _)| Violation { column, rule: Rule::NoColumn }));
+        let row = MedicationRow {
+            drug_name: m.drug_name().to_string(),
+            dosage_kind: 0,
+            morning: None,
+            midday: None,
+            evening: None,
+            night: None,
+            speed: None,
+            duration: None
+        };
+        match m.dosage() {
+            Dosage::Tablet(tablet) if tablet.recurrence() == Recurrence::Daily => {
+                let [morning, midday, evening, night] = tablet.slots();
+                let row = MedicationRow {
+                    dosage_kind: DOSAGE_KIND_TABLET,
+                    morning: tablets_to_column(&mut violations, Column::Morning, morning),
+                    midday: tablets_to_column(&mut violations, Column::Midday, midday),
+                    evening: tablets_to_column(&mut violations, Column::Evening, evening),
+                    night: tablets_to_column(&mut violations, Column::Night, night),
+                    ..row
+                };
+                if violations.is_empty() { Ok(row) } else { Err(RowError { violations }) }
+            }
+            Dosage::Infusion(infusion) => {
+                // The column is in ml/min, which rates such as 1 ml/h aren't
+                // a whole number of thousandths of.
+                let speed = infusion.speed().ml_per_min();
+                if speed.is_none() {
+                    violations.push(Violation { column: Column::Speed, rule: Rule::TooPrecise });
+                }
+                let hours = infusion.duration().whole_hours().ok_or(Rule::NotWholeHours)
+                    .and_then(|h| i32::try_from(h).map_err(|_| Rule::OutOfRange))
+                    .map_err(|rule| violations.push(Violation { column: Column::Duration, rule }))
+                    .ok();
+                match (speed, hours) {
+                    (Some(speed), Some(hours)) if violations.is_empty() => Ok(MedicationRow {
+                        dosage_kind: DOSAGE_KIND_INFUSION,
+                        speed: Some(speed.to_f64()),
+                        duration: Some(hours),
+                        ..row
+                    }),
+                    _ => Err(RowError { violations })
+                }
+            }
+            Dosage::Tablet(_)
+            | Dosage::IntermittentInfusion(_)
+            | Dosage::ContinuousInfusion(_)
+            | Dosage::AsNeeded(_)
+            | Dosage::Tapering(_)
+            | Dosage::SlidingScale(_)
+            | Dosage::LoadingDose(_)
+            | Dosage::Injection(_)
+            | Dosage::Inhaler(_)
+            | Dosage::EyeDrops(_)
+            | Dosage::Patch(_) => {
+                violations.push(Violation {
+                    column: Column::DosageKind, rule: Rule::NotRepresentable
+                });
+                Err(RowError { violations })
+            }
+        }
+    }
+}
+
+impl TryFrom<&MedicationRow> for Medication {
+    type Error = RowError;
+
+    fn try_from(row: &MedicationRow) -> Result<Self, RowError> {
+        let mut violations = Vec::new();
+        if row.drug_name.chars().count() > DRUG_NAME_MAX_LEN {
+            violations.push(Violation { column: Column::DrugName, rule: Rule::TooLong });
+        }
+        let dosage = match row.dosage_kind {
+            DOSAGE_KIND_TABLET => {
+                null(&mut violations, Column::Speed, &row.speed);
+                null(&mut violations, Column::Duration, &row.duration);
+                let morning = non_null(&mut violations, Column::Morning, row.morning)
+                    .and_then(|c| tablets_from_column(&mut violations, Column::Morning, c));
+                let midday = non_null(&mut violations, Column::Midday, row.midday)
+                    .and_then(|c| tablets_from_column(&mut violations, Column::Midday, c));
+                let evening = non_null(&mut violations, Column::Evening, row.evening)
+                    .and_then(|c| tablets_from_column(&mut violations, Column::Evening, c));
+                let night = match row.night {
+                    Some(c) => tablets_from_column(&mut violations, Column::Night, c),
+                    None => Some(TabletCount::ZERO)
+                };
+                match (morning, midday, evening, night) {
+                    (Some(morning), Some(midday), Some(evening), Some(night)) => {
+                        // All slots are zero; blame the ones that can't be null.
+                        let result = Dosage::tablet(morning, midday, evening, night);
+                        if let Err(err) = result {
+                            let rule = Rule::InvalidDosage(err);
+                            violations.extend([Column::Morning, Column::Midday, Column::Evening]
+                                .map(|column| Violation { column, rule }));
+                        }
+                        result.ok()
+                    }
+                    _ => None
+                }
+            }
+            DOSAGE_KIND_INFUSION => {
+                null(&mut violations, Column::Morning, &row.morning);
+                null(&mut violations, Column::Midday, &row.midday);
+                null(&mut violations, Column::Evening, &row.evening);
+                null(&mut violations, Column::Night, &row.night);
+                let speed = non_null(&mut violations, Column::Speed, row.speed)
+                    .and_then(|s| speed_from_column(&mut violations, s));
+                let duration = non_null(&mut violations, Column::Duration, row.duration)
+                    .and_then(|d| duration_from_column(&mut violations, d));
+                match (speed, duration) {
+                    (Some(speed), Some(duration)) =>
+                        dosage(&mut violations, Dosage::infusion(speed, duration)),
+                    _ => None
+                }
+            }
+            _ => {
+                violations.push(Violation { column: Column::DosageKind, rule: Rule::UnknownDosageKind });
+                None
+            }
+        };
+        match dosage {
+            Some(dosage) if violations.is_empty() =>
+                Ok(Medication::new(&row.drug_name, dosage)),
+            _ => Err(RowError { violations })
+        }
+    }
+}
+
+fn null<T>(violations: &mut Vec<Violation>, column: Column, value: &Option<T>) {
+    if value.is_some() {
+        violations.push(Violation { column, rule: Rule::MustBeNull });
+    }
+}
+
+fn non_null<T>(violations: &mut Vec<Violation>, column: Column, value: Option<T>) -> Option<T> {
+    if value.is_none() {
+        violations.push(Violation { column, rule: Rule::MustNotBeNull });
+    }
+    value
+}
+
+fn dosage(violations: &mut Vec<Violation>, result: Result<Dosage, DosageError>) -> Option<Dosage> {
+    result.map_err(|err| violations.push(Violation {
+        column: error_column(err),
+        rule: Rule::InvalidDosage(err)
+    })).ok()
+}
+
+fn error_column(err: DosageError) -> Column {
+    match err {
+        DosageError::InvalidSpeed(_) => Column::Speed,
+        DosageError::ZeroDuration => Column::Duration,
+        _ => Column::DosageKind
+    }
+}
+
+fn tablets_to_column(violations: &mut Vec<Violation>, column: Column, count: TabletCount)
+    -> Option<i32> {
+    let rule = match count.whole() {
+        Some(whole) => match i32::try_from(whole) {
+            Ok(whole) => return Some(whole),
+            Err(_) => Rule::OutOfRange
+        },
+        None => Rule::NotWholeTablets
+    };
+    violations.push(Violation { column, rule });
+    None
+}
+
+fn tablets_from_column(violations: &mut Vec<Violation>, column: Column, count: i32)
+    -> Option<TabletCount> {
+    match u32::try_from(count) {
+        Ok(count) => Some(TabletCount::new(count)),
+        Err(_) => {
+            violations.push(Violation { column, rule: Rule::Negative });
+            None
+        }
+    }
+}
+
+fn speed_from_column(violations: &mut Vec<Violation>, speed: f64) -> Option<FlowRate> {
+    let rule = match Decimal::try_from_f64(speed) {
+        Ok(speed) => match FlowRate::from_ml_per_min(speed) {
+            Ok(speed) => return Some(speed),
+            Err(err) => Rule::InvalidDosage(DosageError::InvalidSpeed(err))
+        },
+        Err(DecimalError::TooPrecise) => Rule::TooPrecise,
+        Err(_) => Rule::OutOfRange
+    };
+    violations.push(Violation { column: Column::Speed, rule });
+    None
+}
+
+fn duration_from_column(violations: &mut Vec<Violation>, hours: i32) -> Option<TimeSpan> {
+    u32::try_from(hours).ok()
+        .and_then(|h| TimeSpan::from_hours(h).ok())
+        .or_else(|| {
+            violations.push(Violation { column: Column::Duration, rule: Rule::OutOfRange });
+            None
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::route::Route;
+    use crate::units::Mass;
+
+    fn tablet_row() -> MedicationRow {
+        MedicationRow {
+            drug_name: "Paracetamol".to_string(),
+            dosage_kind: DOSAGE_KIND_TABLET,
+            morning: Some(1),
+            midday: Some(0),
+            evening: Some(2),
+            night: Some(0),
+            speed: None,
+            duration: None
+        }
+    }
+
+    fn infusion_row() -> MedicationRow {
+        MedicationRow {
+            drug_name: "Infliximab".to_string(),
+            dosage_kind: DOSAGE_KIND_INFUSION,
+            speed: Some(1.5),
+            duration: Some(2),
+            morning: None,
+            midday: None,
+            evening: None,
+            night: None
+        }
+    }
+
+    fn violations(row: &MedicationRow) -> Vec<Violation> {
+        Medication::try_from(row).unwrap_err().violations
+    }
+
+    #[test]
+    fn rows_round_trip() {
+        for row in [tablet_row(), infusion_row()] {
+            let m = Medication::try_from(&row).unwrap();
+            assert_eq!(MedicationRow::try_from(&m), Ok(row));
+        }
+    }
+
+    #[test]
+    fn each_violation_names_its_column_and_rule() {
+        let row = MedicationRow {
+            morning: None, midday: Some(-1), speed: Some(1.5), ..tablet_row()
+        };
+        assert_eq!(violations(&row), [
+            Violation { column: Column::Speed, rule: Rule::MustBeNull },
+            Violation { column: Column::Morning, rule: Rule::MustNotBeNull },
+            Violation { column: Column::Midday, rule: Rule::Negative }
+        ]);
+        let row = MedicationRow { speed: Some(0.0001), duration: Some(-1), ..infusion_row() };
+        assert_eq!(violations(&row), [
+            Violation { column: Column::Speed, rule: Rule::TooPrecise },
+            Violation { column: Column::Duration, rule: Rule::OutOfRange }
+        ]);
+        let row = MedicationRow { duration: Some(0), ..infusion_row() };
+        let rule = Rule::InvalidDosage(DosageError::ZeroDuration);
+        assert_eq!(violations(&row), [Violation { column: Column::Duration, rule }]);
+        let row = MedicationRow { dosage_kind: 3, ..tablet_row() };
+        assert_eq!(violations(&row), [
+            Violation { column: Column::DosageKind, rule: Rule::UnknownDosageKind }
+        ]);
+    }
+
+    #[test]
+    fn tablet_row_without_tablets_is_rejected() {
+        let row = MedicationRow { morning: Some(0), evening: Some(0), ..tablet_row() };
+        let rule = Rule::InvalidDosage(DosageError::ZeroDose);
+        assert_eq!(violations(&row), [
+            Violation { column: Column::Morning, rule },
+            Violation { column: Column::Midday, rule },
+            Violation { column: Column::Evening, rule }
+        ]);
+    }
+
+    #[test]
+    fn medication_the_table_cannot_hold_is_rejected() {
+        let m = Medication::try_from(&tablet_row()).unwrap()
+            .with_strength(Mass::from_mg(Decimal::from_units(500_000)).unwrap())
+            .with_route(Route::FeedingTube).unwrap();
+        assert_eq!(MedicationRow::try_from(&m).unwrap_err().violations, [
+            Violation { column: Column::Strength, rule: Rule::NoColumn },
+            Violation { column: Column::Route, rule: Rule::NoColumn }
+        ]);
+        let quarter = Dosage::tablet(TabletCount::QUARTER, TabletCount::ZERO, TabletCount::ZERO,
+                                     TabletCount::ZERO).unwrap();
+        let m = Medication::new("Prednisolone", quarter);
+        assert_eq!(MedicationRow::try_from(&m).unwrap_err().violations, [
+            Violation { column: Column::Morning, rule: Rule::NotWholeTablets }
+        ]);
+        let speed = FlowRate::from_ml_per_hour(Decimal::from_units(1000)).unwrap();
+        let m = Medication::new("Morphine", Dosage::infusion(speed, TimeSpan::DAY).unwrap());
+        assert_eq!(MedicationRow::try_from(&m).unwrap_err().violations, [
+            Violation { column: Column::Speed, rule: Rule::TooPrecise }
+        ]);
+    }
+
+    #[test]
+    fn drug_name_too_long_is_rejected_both_ways() {
+        let row = MedicationRow { drug_name: "x".repeat(DRUG_NAME_MAX_LEN), ..tablet_row() };
+        let m = Medication::try_from(&row).unwrap();
+        assert_eq!(MedicationRow::try_from(&m), Ok(row));
+        let m = Medication::new(&"x".repeat(300), m.dosage().clone())
+            .with_strength(Mass::from_mg(Decimal::from_units(500_000)).unwrap());
+        assert_eq!(MedicationRow::try_from(&m).unwrap_err().violations, [
+            Violation { column: Column::DrugName, rule: Rule::TooLong },
+            Violation { column: Column::Strength, rule: Rule::NoColumn }
+        ]);
+        let row = MedicationRow { drug_name: "x".repeat(300), ..tablet_row() };
+        assert_eq!(violations(&row), [Violation { column: Column::DrugName, rule: Rule::TooLong }]);
+    }
+}
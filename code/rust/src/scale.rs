@@ -0,0 +1,145 @@
+use crate::decimal::Decimal;
+use crate::medication::{Dosage, DosageError};
+use crate::units::InternationalUnits;
+
+// Sliding scale such as for insulin: the dose depends on the latest
+// `measurement`, e.g. blood glucose in mg/dl.  The ranges are in
+// ascending order and cover the scale without gaps.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlidingScale {
+    measurement: String,
+    ranges: Vec<ScaleRange>
+}
+
+// Range of measured values `from <= value < to` of a sliding scale.
+// `None` leaves the range open below or above; only the first and the
+// last range of a scale may be open.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScaleRange {
+    pub from: Option<Decimal>,
+    pub to: Option<Decimal>,
+    pub dose: InternationalUnits
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScaleLookup {
+    Dose(InternationalUnits),
+    BelowScale,
+    AboveScale
+}
+
+impl ScaleRange {
+    pub fn contains(&self, value: Decimal) -> bool {
+        self.from.is_none_or(|from| from <= value) && self.to.is_none_or(|to| value < to)
+    }
+}
+
+impl SlidingScale {
+    pub fn new(measurement: &str, ranges: Vec<ScaleRange>) -> Result<SlidingScale, DosageError> {
+        let measurement = measurement.trim();
+        if measurement.is_empty() {
+            return Err(DosageError::EmptyMeasurement);
+        }
+        if ranges.is_empty() {
+            return Err(DosageError::NoRanges);
+        }
+        if ranges.iter().any(|r| matches!((r.from, r.to), (Some(from), Some(to)) if from >= to)) {
+            return Err(DosageError::EmptyRange);
+        }
+        for pair in ranges.windows(2) {
+            match (pair[0].to, pair[1].from) {
+                (Some(to), Some(from)) if to < from => return Err(DosageError::GapBetweenRanges),
+                (Some(to), Some(from)) if to == from => {}
+                _ => return Err(DosageError::RangesOverlap)
+            }
+        }
+        Ok(SlidingScale { measurement: measurement.to_string(), ranges })
+    }
+
+    pub fn measurement(&self) -> &str {
+        &self.measurement
+    }
+
+    // In ascending order, never empty.
+    pub fn ranges(&self) -> &[ScaleRange] {
+        &self.ranges
+    }
+
+    // The dose prescribed for the measured `value`.
+    pub fn resolve(&self, value: Decimal) -> ScaleLookup {
+        match self.ranges.iter().find(|range| range.contains(value)) {
+            Some(range) => ScaleLookup::Dose(range.dose),
+            None if self.ranges[0].from.is_some_and(|from| value < from) => ScaleLookup::BelowScale,
+            None => ScaleLookup::AboveScale
+        }
+    }
+}
+
+impl From<SlidingScale> for Dosage {
+    fn from(scale: SlidingScale) -> Self {
+        Dosage::SlidingScale(scale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(from: Option<i64>, to: Option<i64>, dose: i64) -> ScaleRange {
+        ScaleRange {
+            from: from.map(|from| Decimal::from_int(from).unwrap()),
+            to: to.map(|to| Decimal::from_int(to).unwrap()),
+            dose: InternationalUnits::from_iu(Decimal::from_int(dose).unwrap()).unwrap()
+        }
+    }
+
+    fn lookup(scale: &SlidingScale, value: i64) -> ScaleLookup {
+        scale.resolve(Decimal::from_int(value).unwrap())
+    }
+
+    fn units(dose: i64) -> ScaleLookup {
+        ScaleLookup::Dose(InternationalUnits::from_iu(Decimal::from_int(dose).unwrap()).unwrap())
+    }
+
+    #[test]
+    fn invalid_scales_are_rejected() {
+        let glucose = |ranges| SlidingScale::new("glucose", ranges);
+        assert_eq!(SlidingScale::new(" ", vec![range(None, None, 2)]),
+                   Err(DosageError::EmptyMeasurement));
+        assert_eq!(glucose(vec![]), Err(DosageError::NoRanges));
+        assert_eq!(glucose(vec![range(Some(200), Some(150), 2)]), Err(DosageError::EmptyRange));
+        assert_eq!(glucose(vec![range(Some(150), Some(150), 2)]), Err(DosageError::EmptyRange));
+        assert_eq!(glucose(vec![range(None, Some(150), 2), range(Some(200), None, 4)]),
+                   Err(DosageError::GapBetweenRanges));
+        assert_eq!(glucose(vec![range(None, Some(200), 2), range(Some(150), None, 4)]),
+                   Err(DosageError::RangesOverlap));
+        // Only the first range may be open below and only the last above.
+        assert_eq!(glucose(vec![range(None, Some(150), 2), range(None, Some(200), 4)]),
+                   Err(DosageError::RangesOverlap));
+        assert_eq!(glucose(vec![range(Some(150), None, 2), range(Some(200), None, 4)]),
+                   Err(DosageError::RangesOverlap));
+    }
+
+    #[test]
+    fn value_resolves_to_the_dose_of_its_range() {
+        let scale = SlidingScale::new("glucose", vec![
+            range(Some(150), Some(200), 2),
+            range(Some(200), Some(250), 4)
+        ]).unwrap();
+        assert_eq!(lookup(&scale, 149), ScaleLookup::BelowScale);
+        assert_eq!(lookup(&scale, 150), units(2));
+        assert_eq!(lookup(&scale, 199), units(2));
+        assert_eq!(lookup(&scale, 200), units(4));
+        assert_eq!(lookup(&scale, 250), ScaleLookup::AboveScale);
+    }
+
+    #[test]
+    fn open_scale_covers_every_value() {
+        let scale = SlidingScale::new("glucose", vec![
+            range(None, Some(150), 0),
+            range(Some(150), None, 2)
+        ]).unwrap();
+        assert_eq!(lookup(&scale, -10), units(0));
+        assert_eq!(lookup(&scale, 1000), units(2));
+    }
+}
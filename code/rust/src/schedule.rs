@@ -0,0 +1,490 @@
+use std::fmt;
+
+use chrono::{Datelike, NaiveDateTime, NaiveTime, TimeDelta, Weekday, WeekdaySet};
+
+use crate::format::DosageFormatter;
+use crate::medication::{format_dosage, Dosage};
+use crate::units::{FlowRate, TabletCount, TimeSpan};
+
+// Combinators for building regimens out of tablet and infusion
+// dosages.  A tablet dosage stands for one day of it, starting at the
+// time the schedule gets to it; an infusion for one run.  `expand`
+// turns a schedule into the administrations it prescribes, up to
+// `MAX_OCCURRENCES` of them.
+//
+//     tablets.repeat(5)?.then(infusion).every(TimeSpan::from_hours(7 * 24)?)?
+#[derive(Debug, Clone, PartialEq)]
+pub enum Schedule {
+    #[non_exhaustive]
+    Dose { dosage: Dosage },
+    // `second` starts when `first` is over.
+    #[non_exhaustive]
+    Then { first: Box<Schedule>, second: Box<Schedule> },
+    // `schedule` `times` times in a row.
+    #[non_exhaustive]
+    Repeat { times: u32, schedule: Box<Schedule> },
+    // `schedule` started anew every `period`, forever.
+    #[non_exhaustive]
+    Every { period: TimeSpan, schedule: Box<Schedule> },
+    // Only the administrations of `schedule` before `end`.
+    #[non_exhaustive]
+    Until { end: NaiveDateTime, schedule: Box<Schedule> },
+    // Both schedules, starting at the same time.
+    #[non_exhaustive]
+    Parallel { left: Box<Schedule>, right: Box<Schedule> },
+    // Only the administrations of `schedule` on the given weekdays.
+    #[non_exhaustive]
+    OnWeekdays { weekdays: WeekdaySet, schedule: Box<Schedule> }
+}
+
+// Limit on the administrations and runs of repeated schedules that
+// `expand` goes through, so that a short period and a distant horizon
+// can't exhaust memory.
+pub const MAX_OCCURRENCES: usize = 100_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScheduleError {
+    NotSchedulable,
+    ZeroRepetitions,
+    ZeroPeriod,
+    NoWeekdays,
+    TooManyOccurrences
+}
+
+impl fmt::Display for ScheduleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ScheduleError::NotSchedulable => "only tablet and infusion dosages can be scheduled",
+            ScheduleError::ZeroRepetitions => "schedule must be repeated at least once",
+            ScheduleError::ZeroPeriod => "period must be positive",
+            ScheduleError::NoWeekdays => "at least one weekday must be given",
+            ScheduleError::TooManyOccurrences =>
+                "schedule has too many occurrences before the horizon"
+        })
+    }
+}
+
+impl std::error::Error for ScheduleError {}
+
+// Times of day at which the tablets of each slot are taken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlotTimes {
+    pub morning: NaiveTime,
+    pub midday: NaiveTime,
+    pub evening: NaiveTime,
+    pub night: NaiveTime
+}
+
+impl Default for SlotTimes {
+    fn default() -> Self {
+        SlotTimes {
+            morning: NaiveTime::MIN + TimeDelta::hours(8),
+            midday: NaiveTime::MIN + TimeDelta::hours(12),
+            evening: NaiveTime::MIN + TimeDelta::hours(18),
+            night: NaiveTime::MIN + TimeDelta::hours(22)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dose {
+    Tablets(TabletCount),
+    Infusion { speed: FlowRate, duration: TimeSpan }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Administration {
+    pub at: NaiveDateTime,
+    pub dose: Dose
+}
+
+impl Schedule {
+    pub fn dose(dosage: Dosage) -> Result<Schedule, ScheduleError> {
+        match dosage {
+            Dosage::Tablet(_) | Dosage::Infusion(_) => Ok(Schedule::Dose { dosage }),
+            _ => Err(ScheduleError::NotSchedulable)
+        }
+    }
+
+    pub fn then(self, second: Schedule) -> Schedule {
+        Schedule::Then { first: Box::new(self), second: Box::new(second) }
+    }
+
+    pub fn repeat(self, times: u32) -> Result<Schedule, ScheduleError> {
+        if times == 0 {
+            Err(ScheduleError::ZeroRepetitions)
+        } else {
+            Ok(Schedule::Repeat { times, schedule: Box::new(self) })
+        }
+    }
+
+    pub fn every(self, period: TimeSpan) -> Result<Schedule, ScheduleError> {
+        if period.is_zero() {
+            Err(ScheduleError::ZeroPeriod)
+        } else {
+            Ok(Schedule::Every { period, schedule: Box::new(self) })
+        }
+    }
+
+    pub fn until(self, end: NaiveDateTime) -> Schedule {
+        Schedule::Until { end, schedule: Box::new(self) }
+    }
+
+    pub fn parallel(self, right: Schedule) -> Schedule {
+        Schedule::Parallel { left: Box::new(self), right: Box::new(right) }
+    }
+
+    pub fn on_weekdays(self, weekdays: WeekdaySet) -> Result<Schedule, ScheduleError> {
+        if weekdays.is_empty() {
+            Err(ScheduleError::NoWeekdays)
+        } else {
+            Ok(Schedule::OnWeekdays { weekdays, schedule: Box::new(self) })
+        }
+    }
+
+    // When the schedule is over if started at `start`, `None` if it
+    // runs forever.  Ends past the last representable time are given as
+    // `NaiveDateTime::MAX`.
+    pub fn end(&self, start: NaiveDateTime) -> Option<NaiveDateTime> {
+        match *self {
+            Schedule::Dose { ref dosage } => Some(later(start, match *dosage {
+                Dosage::Infusion(infusion) => minutes(infusion.duration()),
+                _ => TimeDelta::days(1)
+            })),
+            Schedule::Then { ref first, ref second } => second.end(first.end(start)?),
+            Schedule::Repeat { times, ref schedule } => {
+                let mut at = start;
+                for _ in 0..times {
+                    match schedule.end(at)? {
+                        next if next == at => break,
+                        next => at = next
+                    }
+                }
+                Some(at)
+            }
+            Schedule::Every { .. } => None,
+            Schedule::Until { end, ref schedule } =>
+                Some(schedule.end(start).map_or(end, |e| e.min(end)).max(start)),
+            Schedule::Parallel { ref left, ref right } =>
+                Some(left.end(start)?.max(right.end(start)?)),
+            Schedule::OnWeekdays { ref schedule, .. } => schedule.end(start)
+        }
+    }
+
+    // The administrations of the schedule started at `start` that fall
+    // before `horizon`, in chronological order.  Fails if getting there
+    // takes more than `MAX_OCCURRENCES` administrations and runs.
+    pub fn expand(&self, start: NaiveDateTime, horizon: NaiveDateTime, slots: &SlotTimes)
+        -> Result<Vec<Administration>, ScheduleError> {
+        let mut administrations = Vec::new();
+        let mut budget = MAX_OCCURRENCES;
+        self.expand_into(start, horizon, slots, &mut administrations, &mut budget)?;
+        administrations.sort_by_key(|a| a.at);
+        Ok(administrations)
+    }
+
+    fn expand_into(&self, start: NaiveDateTime, horizon: NaiveDateTime, slots: &SlotTimes,
+                   out: &mut Vec<Administration>, budget: &mut usize)
+        -> Result<(), ScheduleError> {
+        if start >= horizon {
+            return Ok(());
+        }
+        match *self {
+            Schedule::Dose { ref dosage } => match *dosage {
+                Dosage::Tablet(tablet) => {
+                    let [morning, midday, evening, night] = tablet.slots();
+                    let end = later(start, TimeDelta::days(1)).min(horizon);
+                    let doses = [(slots.morning, morning), (slots.midday, midday),
+                                 (slots.evening, evening), (slots.night, night)];
+                    for date in [Some(start.date()), start.date().succ_opt()].into_iter().flatten() {
+                        if !tablet.recurrence().is_due(date) {
+                            continue;
+                        }
+                        for &(time, count) in &doses {
+                            let at = date.and_time(time);
+                            if !count.is_zero() && start <= at && at < end {
+                                spend(budget)?;
+                                out.push(Administration { at, dose: Dose::Tablets(count) });
+                            }
+                        }
+                    }
+                }
+                Dosage::Infusion(infusion) => {
+                    spend(budget)?;
+                    out.push(Administration {
+                        at: start,
+                        dose: Dose::Infusion {
+                            speed: infusion.speed(), duration: infusion.duration()
+                        }
+                    });
+                }
+                _ => {}
+            },
+            Schedule::Then { ref first, ref second } => {
+                first.expand_into(start, horizon, slots, out, budget)?;
+                if let Some(next) = first.end(start) {
+                    second.expand_into(next, horizon, slots, out, budget)?;
+                }
+            }
+            Schedule::Repeat { times, ref schedule } => {
+                let mut at = start;
+                for _ in 0..times {
+                    spend(budget)?;
+                    schedule.expand_into(at, horizon, slots, out, budget)?;
+                    // Schedules taking no time prescribe nothing.
+                    match schedule.end(at) {
+                        Some(next) if next > at && next < horizon => at = next,
+                        _ => break
+                    }
+                }
+            }
+            Schedule::Every { period, ref schedule } => {
+                let mut at = Some(start);
+                while let Some(run) = at.filter(|&at| at < horizon) {
+                    spend(budget)?;
+                    schedule.expand_into(run, horizon, slots, out, budget)?;
+                    at = run.checked_add_signed(minutes(period)).filter(|&next| next > run);
+                }
+            }
+            Schedule::Until { end, ref schedule } =>
+                schedule.expand_into(start, horizon.min(end), slots, out, budget)?,
+            Schedule::Parallel { ref left, ref right } => {
+                left.expand_into(start, horizon, slots, out, budget)?;
+                right.expand_into(start, horizon, slots, out, budget)?;
+            }
+            Schedule::OnWeekdays { weekdays, ref schedule } => {
+                let mut inner = Vec::new();
+                schedule.expand_into(start, horizon, slots, &mut inner, budget)?;
+                out.extend(inner.into_iter().filter(|a| weekdays.contains(a.at.weekday())));
+            }
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Schedule::Dose { .. } => "dose",
+            Schedule::Then { .. } => "then",
+            Schedule::Repeat { .. } => "repeat",
+            Schedule::Every { .. } => "every",
+            Schedule::Until { .. } => "until",
+            Schedule::Parallel { .. } => "parallel",
+            Schedule::OnWeekdays { .. } => "on_weekdays"
+        }
+    }
+
+    // The non-schedule argument of a combinator and its operands.
+    fn parts(&self) -> (Option<String>, Vec<&Schedule>) {
+        match *self {
+            Schedule::Dose { ref dosage } => (Some(format_dosage(dosage)), vec![]),
+            Schedule::Then { ref first, ref second } => (None, vec![first, second]),
+            Schedule::Repeat { times, ref schedule } => (Some(times.to_string()), vec![schedule]),
+            Schedule::Every { period, ref schedule } => (Some(format_period(period)), vec![schedule]),
+            Schedule::Until { end, ref schedule } =>
+                (Some(end.format("%Y-%m-%d %H:%M").to_string()), vec![schedule]),
+            Schedule::Parallel { ref left, ref right } => (None, vec![left, right]),
+            Schedule::OnWeekdays { weekdays, ref schedule } => {
+                let days: Vec<String> = weekdays.iter(Weekday::Mon).map(|d| d.to_string()).collect();
+                (Some(days.join(" ")), vec![schedule])
+            }
+        }
+    }
+
+    fn write_indented(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        let (argument, operands) = self.parts();
+        write!(f, "{:indent$}", "", indent = 2 * depth)?;
+        match (self, argument) {
+            (Schedule::Dose { .. }, Some(dosage)) => writeln!(f, "{dosage}")?,
+            (_, Some(argument)) => writeln!(f, "{} {argument}", self.name())?,
+            (_, None) => writeln!(f, "{}", self.name())?
+        }
+        operands.into_iter().try_for_each(|operand| operand.write_indented(f, depth + 1))
+    }
+}
+
+// then(repeat(5, 1-0-1), 1.5 ml/min for 2h); the alternate form `{:#}`
+// prints one combinator per line, with its operands indented below it.
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return self.write_indented(f, 0);
+        }
+        let (argument, operands) = self.parts();
+        if let (Schedule::Dose { .. }, Some(dosage)) = (self, &argument) {
+            return f.write_str(dosage);
+        }
+        write!(f, "{}(", self.name())?;
+        let arguments = argument.into_iter().chain(operands.into_iter().map(|s| s.to_string()));
+        write!(f, "{})", arguments.collect::<Vec<_>>().join(", "))
+    }
+}
+
+// 7d, 12h, 1h 30min
+fn format_period(period: TimeSpan) -> String {
+    let day = TimeSpan::DAY.minutes();
+    if period.minutes().is_multiple_of(day) {
+        format!("{}d", period.minutes() / day)
+    } else {
+        DosageFormatter::new().format_time_span(period)
+    }
+}
+
+fn minutes(span: TimeSpan) -> TimeDelta {
+    TimeDelta::minutes(span.minutes().into())
+}
+
+fn later(at: NaiveDateTime, delta: TimeDelta) -> NaiveDateTime {
+    at.checked_add_signed(delta).unwrap_or(NaiveDateTime::MAX)
+}
+
+fn spend(budget: &mut usize) -> Result<(), ScheduleError> {
+    *budget = budget.checked_sub(1).ok_or(ScheduleError::TooManyOccurrences)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::NaiveDate;
+
+    use crate::decimal::Decimal;
+
+    // 2024-09-02 is a Monday.
+    fn at(day: u32, hour: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 9, day).unwrap().and_hms_opt(hour, 0, 0).unwrap()
+    }
+
+    // 1-0-1
+    fn tablets() -> Schedule {
+        let (one, zero) = (TabletCount::new(1), TabletCount::ZERO);
+        Schedule::dose(Dosage::tablet(one, zero, one, zero).unwrap()).unwrap()
+    }
+
+    // 1.5 ml/min for 2h
+    fn infusion() -> Schedule {
+        let speed = FlowRate::from_ml_per_min(Decimal::from_units(1500)).unwrap();
+        Schedule::dose(Dosage::infusion(speed, TimeSpan::from_minutes(120)).unwrap()).unwrap()
+    }
+
+    fn days(n: u32) -> TimeSpan {
+        TimeSpan::from_hours(24 * n).unwrap()
+    }
+
+    fn times(schedule: &Schedule, start: NaiveDateTime, horizon: NaiveDateTime)
+        -> Vec<NaiveDateTime> {
+        schedule.expand(start, horizon, &SlotTimes::default()).unwrap()
+            .into_iter().map(|a| a.at).collect()
+    }
+
+    #[test]
+    fn invalid_combinators_are_rejected() {
+        let prn = Dosage::as_needed(TabletCount::new(1), TabletCount::new(3),
+                                    TimeSpan::from_minutes(360), "pain").unwrap();
+        assert_eq!(Schedule::dose(prn), Err(ScheduleError::NotSchedulable));
+        assert_eq!(tablets().repeat(0), Err(ScheduleError::ZeroRepetitions));
+        assert_eq!(tablets().every(TimeSpan::from_minutes(0)), Err(ScheduleError::ZeroPeriod));
+        assert_eq!(tablets().on_weekdays(WeekdaySet::EMPTY), Err(ScheduleError::NoWeekdays));
+    }
+
+    #[test]
+    fn dose_covers_one_day_or_one_run() {
+        let administrations = tablets().expand(at(2, 10), at(9, 0), &SlotTimes::default());
+        let one = Dose::Tablets(TabletCount::new(1));
+        assert_eq!(administrations, Ok(vec![
+            Administration { at: at(2, 18), dose: one },
+            Administration { at: at(3, 8), dose: one }
+        ]));
+        assert_eq!(tablets().end(at(2, 10)), Some(at(3, 10)));
+        assert_eq!(times(&infusion(), at(2, 10), at(9, 0)), [at(2, 10)]);
+        assert_eq!(infusion().end(at(2, 10)), Some(at(2, 12)));
+    }
+
+    #[test]
+    fn then_starts_the_second_schedule_when_the_first_is_over() {
+        let schedule = tablets().then(infusion());
+        assert_eq!(times(&schedule, at(2, 0), at(9, 0)), [at(2, 8), at(2, 18), at(3, 0)]);
+        assert_eq!(schedule.end(at(2, 0)), Some(at(3, 2)));
+    }
+
+    #[test]
+    fn repeat_runs_the_schedule_back_to_back() {
+        let schedule = tablets().repeat(3).unwrap();
+        assert_eq!(times(&schedule, at(2, 0), at(9, 0)),
+                   [at(2, 8), at(2, 18), at(3, 8), at(3, 18), at(4, 8), at(4, 18)]);
+        assert_eq!(schedule.end(at(2, 0)), Some(at(5, 0)));
+        assert_eq!(times(&schedule, at(2, 0), at(3, 12)), [at(2, 8), at(2, 18), at(3, 8)]);
+    }
+
+    #[test]
+    fn every_restarts_the_schedule_each_period() {
+        let schedule = tablets().repeat(2).unwrap().every(days(7)).unwrap();
+        assert_eq!(times(&schedule, at(2, 0), at(16, 0)),
+                   [at(2, 8), at(2, 18), at(3, 8), at(3, 18),
+                    at(9, 8), at(9, 18), at(10, 8), at(10, 18)]);
+        assert_eq!(schedule.end(at(2, 0)), None);
+    }
+
+    #[test]
+    fn until_drops_administrations_from_its_end_on() {
+        let schedule = infusion().every(days(1)).unwrap().until(at(4, 12));
+        assert_eq!(times(&schedule, at(2, 8), at(30, 0)), [at(2, 8), at(3, 8), at(4, 8)]);
+        assert_eq!(schedule.end(at(2, 8)), Some(at(4, 12)));
+        assert_eq!(schedule.end(at(5, 8)), Some(at(5, 8)));
+    }
+
+    #[test]
+    fn parallel_starts_both_schedules_together() {
+        let schedule = tablets().parallel(infusion());
+        assert_eq!(times(&schedule, at(2, 0), at(9, 0)), [at(2, 0), at(2, 8), at(2, 18)]);
+        assert_eq!(schedule.end(at(2, 0)), Some(at(3, 0)));
+        assert_eq!(tablets().parallel(infusion().every(days(1)).unwrap()).end(at(2, 0)), None);
+    }
+
+    #[test]
+    fn on_weekdays_keeps_administrations_on_those_days() {
+        let weekdays = [Weekday::Mon, Weekday::Wed].into_iter().collect();
+        let schedule = infusion().every(days(1)).unwrap().on_weekdays(weekdays).unwrap();
+        assert_eq!(times(&schedule, at(2, 8), at(12, 0)), [at(2, 8), at(4, 8), at(9, 8), at(11, 8)]);
+    }
+
+    #[test]
+    fn expanding_up_to_the_last_date_does_not_overflow() {
+        let start = NaiveDateTime::MAX - TimeDelta::hours(30);
+        let schedule = tablets().every(days(1)).unwrap();
+        assert_eq!(times(&schedule, start, NaiveDateTime::MAX).len(), 3);
+        assert_eq!(tablets().end(start + TimeDelta::hours(12)), Some(NaiveDateTime::MAX));
+        let schedule = infusion().every(TimeSpan::from_minutes(1)).unwrap();
+        assert_eq!(schedule.expand(at(2, 0), NaiveDateTime::MAX, &SlotTimes::default()),
+                   Err(ScheduleError::TooManyOccurrences));
+        let schedule = infusion().until(at(1, 0)).every(TimeSpan::from_minutes(1)).unwrap();
+        assert_eq!(schedule.expand(at(2, 0), NaiveDateTime::MAX, &SlotTimes::default()),
+                   Err(ScheduleError::TooManyOccurrences));
+    }
+
+    #[test]
+    fn display_nests_the_combinators() {
+        let weekdays = [Weekday::Mon, Weekday::Fri].into_iter().collect();
+        let schedule = tablets().repeat(5).unwrap()
+            .then(infusion())
+            .every(days(7)).unwrap()
+            .until(at(30, 12))
+            .parallel(tablets().on_weekdays(weekdays).unwrap());
+        assert_eq!(schedule.to_string(),
+                   "parallel(until(2024-09-30 12:00, every(7d, then(repeat(5, 1-0-1), \
+                    1.5 ml/min for 2h))), on_weekdays(Mon Fri, 1-0-1))");
+        assert_eq!(format!("{:#}", schedule), "\
+parallel
+  until 2024-09-30 12:00
+    every 7d
+      then
+        repeat 5
+          1-0-1
+        1.5 ml/min for 2h
+  on_weekdays Mon Fri
+    1-0-1
+");
+        assert_eq!(infusion().every(TimeSpan::from_minutes(90)).unwrap().to_string(),
+                   "every(1h 30min, 1.5 ml/min for 2h)");
+    }
+}
@@ -0,0 +1,167 @@
+use std::fmt;
+
+use crate::medication::{Dosage, Medication};
+use crate::units::{Mass, TabletCount, TimeSpan, UnitError, Volume};
+
+// Amounts of active ingredient, from the strength of tablets and the
+// concentration of solutions.  Sliding scales and injections are dosed
+// in IU and contribute no mg, nor do puffs, drops and patches.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StrengthError {
+    NoStrength,
+    NoConcentration,
+    Quantity(UnitError)
+}
+
+impl fmt::Display for StrengthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StrengthError::NoStrength => write!(f, "medication has no strength per tablet"),
+            StrengthError::NoConcentration => write!(f, "medication has no concentration"),
+            StrengthError::Quantity(err) => write!(f, "{err}")
+        }
+    }
+}
+
+impl std::error::Error for StrengthError {}
+
+impl From<UnitError> for StrengthError {
+    fn from(err: UnitError) -> Self {
+        StrengthError::Quantity(err)
+    }
+}
+
+impl Medication {
+    // Active ingredient taken morning, midday, evening and at night.
+    // Returns `None` for dosages other than tablets.
+    pub fn mg_per_slot(&self) -> Option<Result<[Mass; 4], StrengthError>> {
+        let Dosage::Tablet(tablet) = *self.dosage() else {
+            return None;
+        };
+        Some(tablet.slots().into_iter()
+            .map(|count| self.tablet_mass(count))
+            .collect::<Result<Vec<_>, _>>()
+            .map(|masses| [masses[0], masses[1], masses[2], masses[3]]))
+    }
+
+    // Active ingredient given within 24 hours, in the sense of
+    // `tablets_per_day` and `volume_per_day`.
+    pub fn mg_per_day(&self) -> Result<Mass, StrengthError> {
+        let tablets = self.dosage().tablets_per_day()?;
+        let volume = self.dosage().volume_per_day()?;
+        let from_tablets = if tablets.is_zero() { Mass::ZERO } else { self.tablet_mass(tablets)? };
+        let from_volume = if volume.is_zero() { Mass::ZERO } else { self.solution_mass(volume)? };
+        Ok(from_tablets.checked_add(from_volume)?)
+    }
+
+    // Active ingredient an infusion delivers per hour while it runs, at
+    // the latest rate for continuous infusions.  Returns `None` for
+    // dosages that aren't infusions.
+    pub fn mg_per_hour(&self) -> Option<Result<Mass, StrengthError>> {
+        let volume = hourly_volume(self.dosage())?;
+        Some(volume.map_err(StrengthError::from).and_then(|volume| self.solution_mass(volume)))
+    }
+
+    fn tablet_mass(&self, count: TabletCount) -> Result<Mass, StrengthError> {
+        Ok(self.strength().ok_or(StrengthError::NoStrength)?.times_tablets(count)?)
+    }
+
+    fn solution_mass(&self, volume: Volume) -> Result<Mass, StrengthError> {
+        Ok(self.concentration().ok_or(StrengthError::NoConcentration)?.mass_in(volume)?)
+    }
+}
+
+fn hourly_volume(dosage: &Dosage) -> Option<Result<Volume, UnitError>> {
+    let hour = TimeSpan::from_minutes(60);
+    match dosage {
+        Dosage::Infusion(infusion) => Some(infusion.speed().volume_over(hour)),
+        Dosage::IntermittentInfusion(infusion) => Some(infusion.volume().ml().checked_mul_int(60)
+            .and_then(|ml| ml.div_int_rounded(infusion.run_time().minutes().into()))
+            .map_err(UnitError::from)
+            .and_then(Volume::from_ml)),
+        Dosage::ContinuousInfusion(infusion) =>
+            Some(infusion.current_rate().rate.volume_over(hour)),
+        Dosage::LoadingDose(loading) => hourly_volume(loading.maintenance()),
+        _ => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::decimal::Decimal;
+    use crate::loading::Bolus;
+    use crate::units::{Concentration, FlowRate};
+
+    fn mg(units: i64) -> Mass {
+        Mass::from_mg(Decimal::from_units(units)).unwrap()
+    }
+
+    // 2 mg/ml
+    fn concentration() -> Concentration {
+        Concentration::from_mg_per_ml(Decimal::from_units(2_000)).unwrap()
+    }
+
+    // 1.5 ml/min for 2h, 90 ml per hour and 180 ml in total
+    fn infusion() -> Dosage {
+        let speed = FlowRate::from_ml_per_min(Decimal::from_units(1_500)).unwrap();
+        Dosage::infusion(speed, TimeSpan::from_minutes(120)).unwrap()
+    }
+
+    fn paracetamol() -> Medication {
+        let half = TabletCount::HALF;
+        let (one, zero) = (TabletCount::new(1), TabletCount::ZERO);
+        Medication::new("Paracetamol", Dosage::tablet(one, zero, half, one).unwrap())
+    }
+
+    #[test]
+    fn tablets_multiply_the_strength() {
+        let m = paracetamol().with_strength(mg(500_000));
+        assert_eq!(m.mg_per_slot(), Some(Ok([mg(500_000), Mass::ZERO, mg(250_000), mg(500_000)])));
+        assert_eq!(m.mg_per_day(), Ok(mg(1_250_000)));
+        assert_eq!(m.mg_per_hour(), None);
+        assert_eq!(paracetamol().mg_per_slot(), Some(Err(StrengthError::NoStrength)));
+        assert_eq!(paracetamol().mg_per_day(), Err(StrengthError::NoStrength));
+    }
+
+    #[test]
+    fn infusions_multiply_the_concentration() {
+        let m = Medication::new("Morphine", infusion()).with_concentration(concentration());
+        assert_eq!(m.mg_per_slot(), None);
+        assert_eq!(m.mg_per_hour(), Some(Ok(mg(180_000))));
+        assert_eq!(m.mg_per_day(), Ok(mg(360_000)));
+        let without = Medication::new("Morphine", infusion());
+        assert_eq!(without.mg_per_hour(), Some(Err(StrengthError::NoConcentration)));
+        assert_eq!(without.mg_per_day(), Err(StrengthError::NoConcentration));
+    }
+
+    #[test]
+    fn intermittent_infusions_are_counted_while_they_run() {
+        // 100 ml over 30min every 8h: 200 ml/h while running, 300 ml a day.
+        let volume = Volume::from_ml(Decimal::from_units(100_000)).unwrap();
+        let dosage = Dosage::intermittent_infusion(volume, TimeSpan::from_minutes(30),
+                                                   TimeSpan::from_hours(8).unwrap()).unwrap();
+        let m = Medication::new("Cefuroxime", dosage).with_concentration(concentration());
+        assert_eq!(m.mg_per_hour(), Some(Ok(mg(400_000))));
+        assert_eq!(m.mg_per_day(), Ok(mg(600_000)));
+    }
+
+    #[test]
+    fn loading_dose_adds_the_bolus_to_the_first_day() {
+        let bolus = Bolus::Volume(Volume::from_ml(Decimal::from_units(5_000)).unwrap());
+        let dosage = Dosage::loading_dose(bolus, infusion()).unwrap();
+        let m = Medication::new("Vancomycin", dosage).with_concentration(concentration());
+        assert_eq!(m.mg_per_hour(), Some(Ok(mg(180_000))));
+        assert_eq!(m.mg_per_day(), Ok(mg(370_000)));
+    }
+
+    #[test]
+    fn forms_without_mg_contribute_nothing() {
+        let m = Medication::new("Salbutamol", Dosage::inhaler(2, 0, 2, 0).unwrap());
+        assert_eq!(m.mg_per_slot(), None);
+        assert_eq!(m.mg_per_hour(), None);
+        assert_eq!(m.mg_per_day(), Ok(Mass::ZERO));
+    }
+}
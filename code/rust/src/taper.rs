@@ -0,0 +1,133 @@
+use chrono::{Days, NaiveDate};
+
+use crate::medication::{Dosage, DosageError};
+
+// Step-wise schedule such as a prednisolone taper: contiguous phases in
+// chronological order, each with a dosage of its own.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tapering {
+    phases: Vec<TaperPhase>
+}
+
+// One step of a tapering regimen: `dosage` from `start` for `days`
+// days.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaperPhase {
+    pub start: NaiveDate,
+    pub days: u32,
+    pub dosage: Dosage
+}
+
+impl TaperPhase {
+    // The day after the last day of the phase, `None` if that is past
+    // the last date `NaiveDate` can represent.
+    pub fn end(&self) -> Option<NaiveDate> {
+        self.start.checked_add_days(Days::new(self.days.into()))
+    }
+
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.start <= date && self.end().is_none_or(|end| date < end)
+    }
+}
+
+impl Tapering {
+    pub fn new(phases: Vec<TaperPhase>) -> Result<Tapering, DosageError> {
+        if phases.is_empty() {
+            Err(DosageError::NoPhases)
+        } else if phases.iter().any(|phase| phase.days == 0) {
+            Err(DosageError::ZeroPhaseDuration)
+        } else if phases.iter().any(|phase| phase.end().is_none()) {
+            Err(DosageError::PhaseOutOfRange)
+        } else if phases.iter().any(|phase| matches!(phase.dosage, Dosage::Tapering(_))) {
+            Err(DosageError::NestedTapering)
+        } else if phases.windows(2).any(|w| w[0].end() != Some(w[1].start)) {
+            Err(DosageError::PhasesNotContiguous)
+        } else {
+            Ok(Tapering { phases })
+        }
+    }
+
+    // In chronological order, never empty.
+    pub fn phases(&self) -> &[TaperPhase] {
+        &self.phases
+    }
+
+    pub fn start(&self) -> NaiveDate {
+        self.phases[0].start
+    }
+
+    // The dosage of the phase in effect on `date`, `None` before the
+    // start or after the end.
+    pub fn dosage_on(&self, date: NaiveDate) -> Option<&Dosage> {
+        self.phases.iter().find(|phase| phase.contains(date)).map(|phase| &phase.dosage)
+    }
+}
+
+impl From<Tapering> for Dosage {
+    fn from(tapering: Tapering) -> Self {
+        Dosage::Tapering(tapering)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::units::TabletCount;
+
+    fn tablets(morning: u32) -> Dosage {
+        let zero = TabletCount::ZERO;
+        Dosage::tablet(TabletCount::new(morning), zero, zero, zero).unwrap()
+    }
+
+    fn day(n: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 9, n).unwrap()
+    }
+
+    fn phase(start: NaiveDate, days: u32, morning: u32) -> TaperPhase {
+        TaperPhase { start, days, dosage: tablets(morning) }
+    }
+
+    // 4 tablets on the 10th to 12th, 2 on the 13th to 14th, 1 on the 15th.
+    fn prednisolone() -> Tapering {
+        Tapering::new(vec![phase(day(10), 3, 4), phase(day(13), 2, 2), phase(day(15), 1, 1)])
+            .unwrap()
+    }
+
+    #[test]
+    fn invalid_tapering_regimens_are_rejected() {
+        assert_eq!(Tapering::new(vec![]), Err(DosageError::NoPhases));
+        assert_eq!(Tapering::new(vec![phase(day(10), 0, 4)]), Err(DosageError::ZeroPhaseDuration));
+        let nested = TaperPhase { dosage: prednisolone().into(), ..phase(day(10), 3, 4) };
+        assert_eq!(Tapering::new(vec![nested]), Err(DosageError::NestedTapering));
+    }
+
+    #[test]
+    fn phases_must_be_contiguous() {
+        let gap = vec![phase(day(10), 3, 4), phase(day(14), 2, 2)];
+        assert_eq!(Tapering::new(gap), Err(DosageError::PhasesNotContiguous));
+        let overlap = vec![phase(day(10), 3, 4), phase(day(12), 2, 2)];
+        assert_eq!(Tapering::new(overlap), Err(DosageError::PhasesNotContiguous));
+        let out_of_order = vec![phase(day(13), 2, 2), phase(day(10), 3, 4)];
+        assert_eq!(Tapering::new(out_of_order), Err(DosageError::PhasesNotContiguous));
+    }
+
+    #[test]
+    fn phase_ending_past_the_last_date_is_rejected() {
+        let last = vec![phase(NaiveDate::MAX, 1, 1)];
+        assert_eq!(Tapering::new(last), Err(DosageError::PhaseOutOfRange));
+        let long = vec![phase(day(10), 4_000_000_000, 4), phase(day(13), 2, 2)];
+        assert_eq!(Tapering::new(long), Err(DosageError::PhaseOutOfRange));
+    }
+
+    #[test]
+    fn dosage_is_that_of_the_phase_in_effect() {
+        let tapering = prednisolone();
+        assert_eq!(tapering.dosage_on(day(9)), None);
+        assert_eq!(tapering.dosage_on(day(10)), Some(&tablets(4)));
+        assert_eq!(tapering.dosage_on(day(12)), Some(&tablets(4)));
+        assert_eq!(tapering.dosage_on(day(13)), Some(&tablets(2)));
+        assert_eq!(tapering.dosage_on(day(15)), Some(&tablets(1)));
+        assert_eq!(tapering.dosage_on(day(16)), None);
+    }
+}
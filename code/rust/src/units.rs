@@ -0,0 +1,301 @@
+use std::fmt;
+
+use crate::decimal::{Decimal, DecimalError};
+
+// Physical quantities used by dosages.  Volumes are kept in ml and
+// flow rates in ml/h, both as exact decimals, so that rates set in
+// ml/min and in ml/h are exact alike; time spans are whole minutes.
+// Tablet counts are multiples of a quarter tablet.  Doses of drugs
+// such as insulin are given in international units (IU).  Amounts of
+// active ingredient are kept in mg, concentrations in mg/ml.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnitError {
+    Negative,
+    Overflow,
+    NotQuarterTablets,
+    Unbounded
+}
+
+impl fmt::Display for UnitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            UnitError::Negative => "quantity must not be negative",
+            UnitError::Overflow => "quantity is too large",
+            UnitError::NotQuarterTablets => "tablet count must be a multiple of a quarter tablet",
+            UnitError::Unbounded => "quantity is unbounded"
+        })
+    }
+}
+
+impl std::error::Error for UnitError {}
+
+impl From<DecimalError> for UnitError {
+    fn from(_: DecimalError) -> Self {
+        UnitError::Overflow
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Volume(Decimal);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FlowRate(Decimal);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TimeSpan(u32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TabletCount(u64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct InternationalUnits(Decimal);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Mass(Decimal);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Concentration(Decimal);
+
+impl Volume {
+    pub const ZERO: Volume = Volume(Decimal::ZERO);
+
+    pub fn from_ml(ml: Decimal) -> Result<Volume, UnitError> {
+        non_negative(ml).map(Volume)
+    }
+
+    pub fn ml(self) -> Decimal {
+        self.0
+    }
+
+    pub fn checked_add(self, other: Volume) -> Result<Volume, UnitError> {
+        Ok(Volume(self.0.checked_add(other.0)?))
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.0.is_zero()
+    }
+}
+
+impl FlowRate {
+    pub fn from_ml_per_min(ml_per_min: Decimal) -> Result<FlowRate, UnitError> {
+        Ok(FlowRate(non_negative(ml_per_min)?.checked_mul_int(60)?))
+    }
+
+    pub fn from_ml_per_hour(ml_per_hour: Decimal) -> Result<FlowRate, UnitError> {
+        non_negative(ml_per_hour).map(FlowRate)
+    }
+
+    // Only defined if the rate is a whole number of thousandths ml/min.
+    pub fn ml_per_min(self) -> Option<Decimal> {
+        let units = self.0.units();
+        if units % 60 == 0 { Some(Decimal::from_units(units / 60)) } else { None }
+    }
+
+    pub fn ml_per_hour(self) -> Decimal {
+        self.0
+    }
+
+    // Rounded to the precision of `Decimal`.
+    pub fn volume_over(self, duration: TimeSpan) -> Result<Volume, UnitError> {
+        Ok(Volume(self.0.checked_mul_int(i64::from(duration.0))?.div_int_rounded(60)?))
+    }
+
+    // Rounded to the precision of `Decimal`.
+    pub fn volume_over_seconds(self, seconds: u64) -> Result<Volume, UnitError> {
+        let seconds = i64::try_from(seconds).map_err(|_| UnitError::Overflow)?;
+        Ok(Volume(self.0.checked_mul_int(seconds)?.div_int_rounded(3600)?))
+    }
+}
+
+impl TimeSpan {
+    pub const DAY: TimeSpan = TimeSpan(24 * 60);
+
+    pub fn from_minutes(minutes: u32) -> TimeSpan {
+        TimeSpan(minutes)
+    }
+
+    pub fn from_hours(hours: u32) -> Result<TimeSpan, UnitError> {
+        hours.checked_mul(60).map(TimeSpan).ok_or(UnitError::Overflow)
+    }
+
+    pub fn minutes(self) -> u32 {
+        self.0
+    }
+
+    // Only defined if the time span is a whole number of hours.
+    pub fn whole_hours(self) -> Option<u32> {
+        if self.0.is_multiple_of(60) { Some(self.0 / 60) } else { None }
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl fmt::Display for Volume {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ml", self.0)
+    }
+}
+
+impl TabletCount {
+    pub const ZERO: TabletCount = TabletCount(0);
+    pub const QUARTER: TabletCount = TabletCount(1);
+    pub const HALF: TabletCount = TabletCount(2);
+
+    pub fn new(whole: u32) -> TabletCount {
+        TabletCount(u64::from(whole) * 4)
+    }
+
+    pub fn from_quarters(quarters: u64) -> TabletCount {
+        TabletCount(quarters)
+    }
+
+    pub fn from_decimal(count: Decimal) -> Result<TabletCount, UnitError> {
+        let quarters = non_negative(count)?.checked_mul_int(4)?;
+        let one = Decimal::from_int(1)?;
+        if quarters.units() % one.units() != 0 {
+            return Err(UnitError::NotQuarterTablets);
+        }
+        Ok(TabletCount((quarters.units() / one.units()).unsigned_abs()))
+    }
+
+    pub fn to_decimal(self) -> Result<Decimal, UnitError> {
+        let quarters = i64::try_from(self.0).map_err(|_| UnitError::Overflow)?;
+        Ok(Decimal::from_int(quarters)?.div_int_rounded(4)?)
+    }
+
+    pub fn quarters(self) -> u64 {
+        self.0
+    }
+
+    // Only defined for whole tablets.
+    pub fn whole(self) -> Option<u64> {
+        if self.0.is_multiple_of(4) { Some(self.0 / 4) } else { None }
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn checked_add(self, other: TabletCount) -> Result<TabletCount, UnitError> {
+        self.0.checked_add(other.0).map(TabletCount).ok_or(UnitError::Overflow)
+    }
+}
+
+impl InternationalUnits {
+    pub const ZERO: InternationalUnits = InternationalUnits(Decimal::ZERO);
+
+    pub fn from_iu(iu: Decimal) -> Result<InternationalUnits, UnitError> {
+        non_negative(iu).map(InternationalUnits)
+    }
+
+    pub fn iu(self) -> Decimal {
+        self.0
+    }
+
+    pub fn checked_add(self, other: InternationalUnits) -> Result<InternationalUnits, UnitError> {
+        Ok(InternationalUnits(self.0.checked_add(other.0)?))
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.0.is_zero()
+    }
+}
+
+impl fmt::Display for InternationalUnits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} IU", self.0)
+    }
+}
+
+impl Mass {
+    pub const ZERO: Mass = Mass(Decimal::ZERO);
+
+    pub fn from_mg(mg: Decimal) -> Result<Mass, UnitError> {
+        non_negative(mg).map(Mass)
+    }
+
+    pub fn mg(self) -> Decimal {
+        self.0
+    }
+
+    pub fn checked_add(self, other: Mass) -> Result<Mass, UnitError> {
+        Ok(Mass(self.0.checked_add(other.0)?))
+    }
+
+    // `self` per tablet, rounded to the precision of `Decimal`.
+    pub fn times_tablets(self, count: TabletCount) -> Result<Mass, UnitError> {
+        Ok(Mass(self.0.checked_mul(count.to_decimal()?)?))
+    }
+}
+
+impl Concentration {
+    pub fn from_mg_per_ml(mg_per_ml: Decimal) -> Result<Concentration, UnitError> {
+        non_negative(mg_per_ml).map(Concentration)
+    }
+
+    pub fn mg_per_ml(self) -> Decimal {
+        self.0
+    }
+
+    // Rounded to the precision of `Decimal`.
+    pub fn mass_in(self, volume: Volume) -> Result<Mass, UnitError> {
+        Ok(Mass(self.0.checked_mul(volume.ml())?))
+    }
+}
+
+impl fmt::Display for Mass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} mg", self.0)
+    }
+}
+
+impl fmt::Display for Concentration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} mg/ml", self.0)
+    }
+}
+
+// 2, ½, 1¾
+impl fmt::Display for TabletCount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (whole, fraction) = (self.0 / 4, match self.0 % 4 {
+            1 => "¼",
+            2 => "½",
+            3 => "¾",
+            _ => ""
+        });
+        if whole == 0 && !fraction.is_empty() {
+            f.write_str(fraction)
+        } else {
+            write!(f, "{whole}{fraction}")
+        }
+    }
+}
+
+fn non_negative(x: Decimal) -> Result<Decimal, UnitError> {
+    if x.is_negative() { Err(UnitError::Negative) } else { Ok(x) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_in_ml_per_hour_is_exact() {
+        let rate = FlowRate::from_ml_per_hour(Decimal::from_units(1000)).unwrap();
+        assert_eq!(rate.ml_per_hour(), Decimal::from_units(1000));
+        assert_eq!(rate.ml_per_min(), None);
+        assert_eq!(rate.volume_over(TimeSpan::DAY), Volume::from_ml(Decimal::from_units(24000)));
+    }
+
+    #[test]
+    fn rate_in_ml_per_min_is_exact() {
+        let rate = FlowRate::from_ml_per_min(Decimal::from_units(1500)).unwrap();
+        assert_eq!(rate.ml_per_min(), Some(Decimal::from_units(1500)));
+        assert_eq!(rate.ml_per_hour(), Decimal::from_units(90000));
+        assert_eq!(rate, FlowRate::from_ml_per_hour(Decimal::from_units(90000)).unwrap());
+    }
+}